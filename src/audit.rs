@@ -0,0 +1,36 @@
+use std::env;
+use std::fs;
+use serde::Deserialize;
+
+use crate::AppContext;
+
+// who triggered the builds, always written to the journal, and onto the builds when configured
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct AuditConfig {
+    // name of a parameter sent to every build with `user@host: arguments`, like TRIGGERED_BY
+    pub parameter: Option<String>,
+    // set the description of every build to the same
+    pub description: Option<bool>,
+}
+
+pub fn user() -> String {
+    ["USER", "USERNAME", "LOGNAME"].iter().find_map(|v| env::var(v).ok().filter(|v| !v.is_empty()))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+pub fn host() -> String {
+    ["HOSTNAME", "COMPUTERNAME"].iter().find_map(|v| env::var(v).ok().filter(|v| !v.is_empty()))
+        .or_else(|| fs::read_to_string("/etc/hostname").ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty()))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+// the arguments of the run, without the secrets they may hold
+pub fn args(ctx: &AppContext) -> Vec<String> {
+    env::args().skip(1).map(|v| ctx.redact(&v)).collect()
+}
+
+// `user@host: jenkins-build <arguments>`
+pub fn triggered_by(ctx: &AppContext) -> String {
+    format!("{}@{}: jenkins-build {}", user(), host(), args(ctx).join(" "))
+}
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time;
+
+use crate::JenkinsResult;
+
+// several builds followed in the same folder get their results from one request to the folder,
+// `jobs[builds[url,result,…]{0,10}]`, instead of one request per build and per poll
+#[derive(Default, Debug)]
+pub struct Batches {
+    // the builds followed in each folder
+    followers: Mutex<HashMap<String, usize>>,
+    // the last answer of each folder by build url, and when its request went out
+    snapshots: Mutex<HashMap<String, Arc<FolderSnapshot>>>,
+}
+
+pub type Snapshot = Arc<HashMap<String, JenkinsResult>>;
+
+// held across the request to its folder only, the other folders are fetched meanwhile
+pub type FolderSnapshot = tokio::sync::Mutex<Option<(time::Instant, Snapshot)>>;
+
+// the folder holding the job of a build, `…/job/team/` for `…/job/team/job/api/12/` and the root of
+// the instance for a top level job
+pub fn folder(build_url: &str) -> Option<String> {
+    let (job, number) = build_url.trim_end_matches('/').rsplit_once('/')?;
+    number.parse::<u64>().ok()?;
+    let (parent, _) = job.rsplit_once('/')?;
+    parent.strip_suffix("job").map(|v| v.to_string())
+}
+
+impl Batches {
+    // counted as followed until the guard is dropped
+    pub fn follow(&self, build_url: &str) -> Option<Follow<'_>> {
+        let folder = folder(build_url)?;
+        *self.followers.lock().unwrap().entry(folder.clone()).or_default() += 1;
+        Some(Follow { batches: self, folder })
+    }
+
+    pub fn snapshot(&self, folder: &str) -> Arc<FolderSnapshot> {
+        self.snapshots.lock().unwrap().entry(folder.to_string()).or_default().clone()
+    }
+
+    // a single build is cheaper to poll by itself than its whole folder
+    pub fn batched(&self, folder: &str) -> bool {
+        self.followers.lock().unwrap().get(folder).copied().unwrap_or_default() > 1
+    }
+}
+
+pub struct Follow<'a> {
+    batches: &'a Batches,
+    folder: String,
+}
+
+impl Drop for Follow<'_> {
+    fn drop(&mut self) {
+        let mut followers = self.batches.followers.lock().unwrap();
+        if let Some(count) = followers.get_mut(&self.folder) {
+            *count -= 1;
+            if *count == 0 {
+                followers.remove(&self.folder);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folder_of_a_build() {
+        assert_eq!(folder("http://ci/job/team/job/api/12/").as_deref(), Some("http://ci/job/team/"));
+        assert_eq!(folder("http://ci/job/team/job/api/12").as_deref(), Some("http://ci/job/team/"));
+        assert_eq!(folder("http://ci/job/api/7/").as_deref(), Some("http://ci/"));
+        assert_eq!(folder("http://ci/jenkins/job/api/7/").as_deref(), Some("http://ci/jenkins/"));
+        // not a build
+        assert_eq!(folder("http://ci/job/team/job/api/"), None);
+        assert_eq!(folder("http://ci/job/api/lastBuild/"), None);
+        assert_eq!(folder("http://ci/view/api/7/"), None);
+        assert_eq!(folder("12"), None);
+    }
+
+    #[test]
+    fn batched_while_followed_twice() {
+        let batches = Batches::default();
+        let first = batches.follow("http://ci/job/team/job/api/1/").unwrap();
+        assert!(!batches.batched("http://ci/job/team/"));
+        let second = batches.follow("http://ci/job/team/job/web/4/").unwrap();
+        assert!(batches.batched("http://ci/job/team/"));
+        assert!(!batches.batched("http://ci/"));
+        drop(first);
+        assert!(!batches.batched("http://ci/job/team/"));
+        drop(second);
+        assert!(batches.followers.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn snapshots_are_per_folder() {
+        let batches = Batches::default();
+        let team = batches.snapshot("http://ci/job/team/");
+        let _held = team.lock().await;
+        // another folder isn't blocked by a request in flight
+        assert!(batches.snapshot("http://ci/").try_lock().is_ok());
+        assert!(batches.snapshot("http://ci/job/team/").try_lock().is_err());
+    }
+}
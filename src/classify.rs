@@ -0,0 +1,63 @@
+use std::fs;
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+
+use crate::pattern::Pattern;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    #[serde(default)]
+    rules: Vec<RuleConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleConfig {
+    id: String,
+    pattern: String,
+    category: String,
+    hint: Option<String>,
+}
+
+// a failure category, found by a pattern matching a line of the console log
+pub struct Rule {
+    pub id: String,
+    pub category: String,
+    pub hint: Option<String>,
+    pattern: Pattern,
+}
+
+impl Rule {
+    // the category and what to do about it, for the report
+    pub fn describe(&self) -> String {
+        match &self.hint {
+            Some(hint) => format!("{}: {}", self.category, hint),
+            None => self.category.clone()
+        }
+    }
+}
+
+// the `failure_rules` file, every pattern is checked up front so a typo shows before the run
+pub fn load(path: &str) -> Result<Vec<Rule>> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read the failure rules {:?}", path))?;
+    let file: RulesFile = toml::from_str(&content).with_context(|| format!("Failed to parse {:?}", path))?;
+    let rules = file.rules.into_iter().map(|v| Ok(Rule {
+        pattern: Pattern::new(&v.pattern).with_context(
+            || format!("Invalid pattern {:?} of the rule {} in {:?}", v.pattern, v.id, path))?,
+        id: v.id,
+        category: v.category,
+        hint: v.hint,
+    })).collect::<Result<Vec<_>>>()?;
+    for (idx, rule) in rules.iter().enumerate() {
+        if rules[..idx].iter().any(|v| v.id == rule.id) {
+            return Err(anyhow!("Duplicate rule {} in {:?}", rule.id, path))
+        }
+    }
+    Ok(rules)
+}
+
+// the first rule in the file order with a matching line, the more specific rules go first
+pub fn classify<'a>(rules: &'a [Rule], log: &str) -> Option<&'a Rule> {
+    rules.iter().find(|rule| log.lines().any(|line| rule.pattern.is_match(line)))
+}
@@ -0,0 +1,68 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+
+use crate::RunState;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyUrls {
+    // the builds of the jobs that didn't succeed
+    Failed,
+    All,
+}
+
+#[cfg(target_os = "macos")]
+fn commands() -> Vec<Vec<&'static str>> {
+    vec![vec!["pbcopy"]]
+}
+
+#[cfg(windows)]
+fn commands() -> Vec<Vec<&'static str>> {
+    vec![vec!["clip"]]
+}
+
+// wayland first, the x11 tools may also be installed but only reach xwayland
+#[cfg(not(any(target_os = "macos", windows)))]
+fn commands() -> Vec<Vec<&'static str>> {
+    let mut commands = Vec::new();
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        commands.push(vec!["wl-copy"]);
+    }
+    commands.push(vec!["xclip", "-selection", "clipboard"]);
+    commands.push(vec!["xsel", "--clipboard", "--input"]);
+    commands
+}
+
+// the first clipboard tool found gets the text on stdin
+fn copy(text: &str) -> Result<()> {
+    let commands = commands();
+    for command in &commands {
+        let mut child = match Command::new(command[0]).args(&command[1..]).stdin(Stdio::piped())
+            .stdout(Stdio::null()).stderr(Stdio::null()).spawn() {
+            Ok(v) => v,
+            Err(_) => continue
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes()).context("Failed to write to the clipboard")?;
+        }
+        let status = child.wait().context("Failed to write to the clipboard")?;
+        if status.success() {
+            return Ok(())
+        }
+    }
+    let names: Vec<&str> = commands.iter().map(|v| v[0]).collect();
+    Err(anyhow!("No clipboard tool found, tried {}", names.join(", ")))
+}
+
+// `--copy-urls`, a build url per line, the number of urls copied
+pub fn copy_urls(state: &RunState, which: CopyUrls) -> Result<usize> {
+    let urls: Vec<&str> = (0..state.jobs.len())
+        .filter(|idx| which == CopyUrls::All || !state.results[*idx].map(|v| state.jobs[*idx].is_success(v)).unwrap_or_default())
+        .map(|idx| state.urls[idx].as_str()).filter(|v| !v.is_empty()).collect();
+    if urls.is_empty() {
+        return Ok(0)
+    }
+    copy(&(urls.join("\n") + "\n"))?;
+    Ok(urls.len())
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::fs;
+use anyhow::{anyhow, Context, Result};
+
+// `KEY=VALUE` lines with `#` comments, an optional `export ` and quotes around the value like a shell
+// would accept them; only the keys of the allow-list may appear, so a reviewed file can't slip in others
+pub fn read(path: &str, keys: &[String]) -> Result<HashMap<String, String>> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read the env file {:?}", path))?;
+    let mut values = HashMap::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line.split_once('=').with_context(
+            || format!("{}:{}: no `=` found in {:?}", path, number + 1, line))?;
+        let key = key.trim();
+        if !keys.iter().any(|v| v == key) {
+            return Err(anyhow!("{}:{}: {} is not in env_file_keys", path, number + 1, key))
+        }
+        let value = value.trim();
+        let value = match (value.chars().next(), value.chars().last()) {
+            (Some(a), Some(b)) if value.len() > 1 && a == b && (a == '"' || a == '\'') => &value[1..value.len() - 1],
+            _ => value
+        };
+        values.insert(key.to_string(), value.to_string());
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_content(name: &str, content: &str, keys: &[&str]) -> Result<HashMap<String, String>> {
+        let path = std::env::temp_dir().join(format!("jenkins-build-env-{}-{}", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        let keys: Vec<String> = keys.iter().map(|v| v.to_string()).collect();
+        let values = read(path.to_str().unwrap(), &keys);
+        let _ = fs::remove_file(&path);
+        values
+    }
+
+    #[test]
+    fn read_values() {
+        let content = "# deploy\n\nexport TAG=v1\n  ENV = 'prod' \nNOTE=\"a = b # c\"\nEMPTY=\nQ=\"\n";
+        let values = read_content("values", content, &["TAG", "ENV", "NOTE", "EMPTY", "Q", "UNUSED"]).unwrap();
+        assert_eq!(values.len(), 5);
+        assert_eq!(values["TAG"], "v1");
+        assert_eq!(values["ENV"], "prod");
+        assert_eq!(values["NOTE"], "a = b # c");
+        assert_eq!(values["EMPTY"], "");
+        // a lone quote is kept
+        assert_eq!(values["Q"], "\"");
+    }
+
+    #[test]
+    fn mismatched_quotes_are_kept() {
+        let values = read_content("quotes", "A='x\"\nB=\"y\"z\r\n", &["A", "B"]).unwrap();
+        assert_eq!(values["A"], "'x\"");
+        assert_eq!(values["B"], "\"y\"z");
+    }
+
+    #[test]
+    fn keys_outside_the_allow_list() {
+        let e = read_content("keys", "TAG=v1\nTOKEN=abc\n", &["TAG"]).unwrap_err();
+        assert!(e.to_string().ends_with(":2: TOKEN is not in env_file_keys"), "{}", e);
+        let e = read_content("equal", "TAG=v1\nexport\n", &["TAG"]).unwrap_err();
+        assert!(e.to_string().contains(":2: no `=` found"), "{}", e);
+        assert!(read("/nonexistent/env", &[]).is_err());
+    }
+}
@@ -0,0 +1,63 @@
+use std::sync::Mutex;
+use std::time;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use crate::BuildResult;
+
+// what happens to a job during the run, identified by the index of the job
+#[derive(Debug, Clone)]
+pub enum JobEvent {
+    // waiting for a lock or a concurrency group held by other jobs, like `lock db-main`
+    Queued { on: String },
+    // the build is waiting in the jenkins queue, in an item created by the trigger or in the identical one it follows
+    Enqueued { queue_item: String, created: bool },
+    // the build was created, after waiting in the jenkins queue unless it was created by an interrupted run
+    Started { build_url: String, queue_wait: Option<time::Duration> },
+    // an intermediate status like STALLED or a restarting jenkins, empty once running normally again
+    StageChanged(String),
+    // may be published again with extra information about hooks and notifications
+    Finished { result: BuildResult, message: String },
+    // no result could be obtained
+    Errored { message: String },
+    // published with the result of a build triggered by the run
+    Timed(JobTimings),
+    // the canary build succeeded and soaked, the full rollout waits for the operator to promote it
+    CanaryPassed { build_url: String },
+}
+
+// the phases of a build triggered by the run, for `--timings`
+#[derive(Debug, Clone, Copy)]
+pub struct JobTimings {
+    pub triggered: time::Instant,
+    // from posting the trigger to the answer of jenkins
+    pub trigger: time::Duration,
+    pub queue: time::Duration,
+    // as reported by jenkins
+    pub build: time::Duration,
+    // the rest of the time until the result was seen
+    pub polling: time::Duration,
+    pub total: time::Duration,
+}
+
+// every subscriber gets every event, none is dropped; the subscriptions end when the bus is dropped
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Mutex<Vec<UnboundedSender<(usize, JobEvent)>>>,
+}
+
+impl EventBus {
+    pub fn subscribe(&self) -> UnboundedReceiver<(usize, JobEvent)> {
+        let (tx, rx) = unbounded_channel();
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(tx);
+        }
+        rx
+    }
+
+    pub fn publish(&self, idx: usize, event: JobEvent) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            // a subscriber that went away doesn't want more events
+            subscribers.retain(|tx| tx.send((idx, event.clone())).is_ok());
+        }
+    }
+}
@@ -0,0 +1,129 @@
+// the characters of the query must appear in order in the candidate, ignoring case; the score favors
+// consecutive characters and the start of the segments of a path like `team/job/payments-api`
+pub fn score(query: &str, candidate: &str) -> Option<i64> {
+    let query: Vec<char> = query.chars().filter(|v| !v.is_whitespace()).flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return Some(0)
+    }
+    let chars: Vec<char> = candidate.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for (idx, c) in chars.iter().enumerate() {
+        if next == query.len() {
+            break
+        }
+        if *c != query[next] {
+            continue
+        }
+        score += 1;
+        if previous == Some(idx.wrapping_sub(1)) {
+            score += 5;
+        }
+        if idx == 0 || matches!(chars[idx - 1], '/' | '-' | '_' | ' ' | '.') {
+            score += 3;
+        }
+        previous = Some(idx);
+        next += 1;
+    }
+    if next < query.len() {
+        return None
+    }
+    // between equal matches the shorter name is the closer one
+    Some(score * 100 - chars.len() as i64)
+}
+
+// the indexes of the candidates matching the query, best first
+pub fn rank<S: AsRef<str>>(query: &str, candidates: &[S]) -> Vec<usize> {
+    let mut matches: Vec<(i64, usize)> = candidates.iter().enumerate()
+        .filter_map(|(idx, v)| score(query, v.as_ref()).map(|score| (score, idx))).collect();
+    matches.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    matches.into_iter().map(|v| v.1).collect()
+}
+
+// the jobs a missing job was probably renamed or moved to, closest first: the same name in another folder,
+// then a name containing the other one or a few characters apart, like `payment-api` for `payments-api`
+pub fn similar<S: AsRef<str>>(name: &str, candidates: &[S]) -> Vec<usize> {
+    let base = |v: &str| v.rsplit("/job/").next().unwrap_or(v).to_lowercase();
+    let wanted = base(name);
+    let mut matches: Vec<(usize, usize)> = candidates.iter().enumerate().filter_map(|(idx, v)| {
+        let other = base(v.as_ref());
+        let distance = match distance(&wanted, &other) {
+            0 => 0,
+            _ if contains(&wanted, &other) || contains(&other, &wanted) => 1,
+            v if v <= wanted.chars().count().max(3) / 3 => v + 1,
+            _ => return None
+        };
+        Some((distance, idx))
+    }).filter(|v| candidates[v.1].as_ref() != name).collect();
+    matches.sort_by_key(|v| (v.0, candidates[v.1].as_ref().len(), v.1));
+    matches.into_iter().map(|v| v.1).collect()
+}
+
+// a part long enough to be the same name, like `payments` of `payments-api`
+fn contains(longer: &str, shorter: &str) -> bool {
+    shorter.chars().count() * 2 >= longer.chars().count() && longer.contains(shorter)
+}
+
+// the levenshtein distance
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substituted = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substituted.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_needs_the_characters_in_order() {
+        assert!(score("pay", "payments-api").is_some());
+        assert!(score("PAPI", "payments-api").is_some());
+        assert!(score("ipa", "payments-api").is_none());
+        assert_eq!(score(" ", "anything"), Some(0));
+    }
+
+    #[test]
+    fn rank_prefers_segment_starts_and_consecutive_characters() {
+        let jobs = ["team/job/api-gateway", "team/job/payments-api", "pxaxyxmxexnxtxs"];
+        assert_eq!(rank("pay", &jobs)[0], 1);
+        assert_eq!(rank("api", &jobs)[..2], [0, 1]);
+        assert_eq!(rank("gw", &jobs), [0]);
+    }
+
+    #[test]
+    fn rank_puts_the_shorter_of_equal_matches_first() {
+        assert_eq!(rank("deploy", &["deploy-prod", "deploy"]), [1, 0]);
+    }
+
+    #[test]
+    fn distance_counts_the_edits() {
+        assert_eq!(distance("payments-api", "payments-api"), 0);
+        assert_eq!(distance("payments-api", "payment-api"), 1);
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn similar_finds_moved_and_renamed_jobs() {
+        let jobs = ["team/job/payments-api", "payment-api", "payments", "api", "a", "orders"];
+        // the same name in a folder first, then a containing name and the close ones
+        assert_eq!(similar("payments-api", &jobs), [0, 2, 1]);
+        assert!(similar("zzzzqqq", &jobs).is_empty());
+    }
+
+    #[test]
+    fn similar_ignores_short_parts_and_the_name_itself() {
+        assert!(similar("vanish-totally-unlike", &["a", "vanish-totally-unlike"]).is_empty());
+    }
+}
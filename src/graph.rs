@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+
+use crate::_JenkinsJobConfig;
+
+// the run as a diagram to review before it happens: the jobs of each stage run in parallel, a manual gate
+// separates the stages, dotted links show the jobs sharing a lock or a concurrency
+// group and the rollback jobs
+pub fn render(jobs: &[_JenkinsJobConfig], dot: bool) -> String {
+    let mut stages: Vec<Vec<usize>> = vec![Vec::new()];
+    for (idx, job) in jobs.iter().enumerate() {
+        if job.gate.is_some() && !stages[stages.len() - 1].is_empty() {
+            stages.push(Vec::new());
+        }
+        stages.last_mut().unwrap().push(idx);
+    }
+    let mut graph = Diagram { mermaid: !dot, out: String::new(), indent: "    " };
+    graph.open();
+    for (number, stage) in stages.iter().enumerate() {
+        let clustered = stages.len() > 1;
+        if clustered {
+            graph.open_stage(number);
+        }
+        for idx in stage {
+            graph.node(&format!("j{}", idx), &label(&jobs[*idx]), false);
+        }
+        if clustered {
+            graph.close_stage();
+        }
+    }
+    for (number, pair) in stages.windows(2).enumerate() {
+        let gate = format!("g{}", number + 1);
+        graph.node(&gate, "manual gate", true);
+        for idx in &pair[0] {
+            graph.edge(&format!("j{}", idx), &gate);
+        }
+        for idx in &pair[1] {
+            graph.edge(&gate, &format!("j{}", idx));
+        }
+    }
+    let mut locks: HashMap<&str, usize> = HashMap::new();
+    let mut groups: HashMap<&str, usize> = HashMap::new();
+    for (idx, job) in jobs.iter().enumerate() {
+        if let Some(lock) = job.lock.as_deref() {
+            if let Some(previous) = locks.insert(lock, idx) {
+                graph.link(&format!("j{}", previous), &format!("j{}", idx), &format!("lock {}", lock), false);
+            }
+        }
+        if let Some(group) = job.concurrency_group.as_deref() {
+            if let Some(previous) = groups.insert(group, idx) {
+                graph.link(&format!("j{}", previous), &format!("j{}", idx), &format!("group {}", group), false);
+            }
+        }
+        if let Some(rollback) = &job.rollback_job {
+            let node = format!("r{}", idx);
+            graph.node(&node, &format!("{} ({})", rollback, job.instance_name), false);
+            graph.link(&format!("j{}", idx), &node, "on failure", true);
+        }
+    }
+    graph.close()
+}
+
+// the name and instance, then what happens to the job besides the build
+fn label(job: &_JenkinsJobConfig) -> String {
+    let mut lines = vec![format!("{} ({})", job.name, job.instance_name)];
+    if job.canary_parameters.is_some() {
+        lines.push(format!("canary, soak {}s", job.soak_seconds));
+    }
+    if let Some(verify) = &job.verify {
+        lines.push(format!("verify {}", crate::redact_urls(&verify.url)));
+    }
+    if let Some(minutes) = job.timeout_minutes {
+        lines.push(format!("timeout {}m", minutes));
+    }
+    lines.join("\n")
+}
+
+// writes either graphviz or mermaid
+struct Diagram {
+    mermaid: bool,
+    out: String,
+    // deeper inside a stage
+    indent: &'static str,
+}
+
+impl Diagram {
+    fn text(&self, s: &str) -> String {
+        if self.mermaid {
+            s.replace('"', "#quot;").replace('\n', "<br/>")
+        } else {
+            s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+        }
+    }
+
+    fn open(&mut self) {
+        self.out += if self.mermaid { "flowchart LR\n" } else { "digraph plan {\n    rankdir=LR;\n" };
+    }
+
+    fn close(mut self) -> String {
+        if !self.mermaid {
+            self.out += "}\n";
+        }
+        self.out
+    }
+
+    fn open_stage(&mut self, number: usize) {
+        self.out += &if self.mermaid {
+            format!("    subgraph stage{} [\"stage {}\"]\n", number + 1, number + 1)
+        } else {
+            format!("    subgraph cluster_{} {{\n        label=\"stage {}\";\n", number + 1, number + 1)
+        };
+        self.indent = "        ";
+    }
+
+    fn close_stage(&mut self) {
+        self.out += if self.mermaid { "    end\n" } else { "    }\n" };
+        self.indent = "    ";
+    }
+
+    fn node(&mut self, id: &str, label: &str, gate: bool) {
+        let label = self.text(label);
+        self.out += &match (self.mermaid, gate) {
+            (true, false) => format!("{}{}[\"{}\"]\n", self.indent, id, label),
+            (true, true) => format!("{}{}{{{{\"{}\"}}}}\n", self.indent, id, label),
+            (false, false) => format!("{}{} [label=\"{}\", shape=box];\n", self.indent, id, label),
+            (false, true) => format!("{}{} [label=\"{}\", shape=hexagon];\n", self.indent, id, label),
+        };
+    }
+
+    fn edge(&mut self, from: &str, to: &str) {
+        self.out += &if self.mermaid {
+            format!("    {} --> {}\n", from, to)
+        } else {
+            format!("    {} -> {};\n", from, to)
+        };
+    }
+
+    // a dotted link, without an arrow unless `directed`
+    fn link(&mut self, from: &str, to: &str, label: &str, directed: bool) {
+        let label = self.text(label);
+        self.out += &match (self.mermaid, directed) {
+            (true, false) => format!("    {} -.-|\"{}\"| {}\n", from, label, to),
+            (true, true) => format!("    {} -.->|\"{}\"| {}\n", from, label, to),
+            (false, false) => format!("    {} -> {} [label=\"{}\", style=dotted, dir=none];\n", from, to, label),
+            (false, true) => format!("    {} -> {} [label=\"{}\", style=dotted];\n", from, to, label),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::context;
+    use crate::{get_job_config, Gate};
+
+    // deploy-db and deploy-api share a lock, the gate before deploy-api starts a second stage
+    async fn plan() -> Vec<_JenkinsJobConfig> {
+        let ctx = context("").await;
+        let mut jobs: Vec<_JenkinsJobConfig> = ["build", "deploy-db", "deploy-api"].iter()
+            .map(|v| get_job_config(&ctx, v, "dev").unwrap()).collect();
+        jobs[1].lock = Some("prod".to_string());
+        jobs[2].lock = Some("prod".to_string());
+        jobs[2].gate = Some(Gate::Manual);
+        jobs[2].rollback_job = Some("rollback-api".to_string());
+        jobs
+    }
+
+    #[tokio::test]
+    async fn render_mermaid() {
+        assert_eq!(render(&plan().await, false), r#"flowchart LR
+    subgraph stage1 ["stage 1"]
+        j0["build (dev)"]
+        j1["deploy-db (dev)"]
+    end
+    subgraph stage2 ["stage 2"]
+        j2["deploy-api (dev)"]
+    end
+    g1{{"manual gate"}}
+    j0 --> g1
+    j1 --> g1
+    g1 --> j2
+    j1 -.-|"lock prod"| j2
+    r2["rollback-api (dev)"]
+    j2 -.->|"on failure"| r2
+"#);
+    }
+
+    #[tokio::test]
+    async fn render_dot() {
+        assert_eq!(render(&plan().await, true), r#"digraph plan {
+    rankdir=LR;
+    subgraph cluster_1 {
+        label="stage 1";
+        j0 [label="build (dev)", shape=box];
+        j1 [label="deploy-db (dev)", shape=box];
+    }
+    subgraph cluster_2 {
+        label="stage 2";
+        j2 [label="deploy-api (dev)", shape=box];
+    }
+    g1 [label="manual gate", shape=hexagon];
+    j0 -> g1;
+    j1 -> g1;
+    g1 -> j2;
+    j1 -> j2 [label="lock prod", style=dotted, dir=none];
+    r2 [label="rollback-api (dev)", shape=box];
+    j2 -> r2 [label="on failure", style=dotted];
+}
+"#);
+    }
+
+    #[tokio::test]
+    async fn render_escapes_the_labels_of_a_single_stage() {
+        let ctx = context("").await;
+        let jobs = vec![get_job_config(&ctx, r#"say "hi""#, "prod").unwrap()];
+        assert_eq!(render(&jobs, false), "flowchart LR\n    j0[\"say #quot;hi#quot; (prod)\"]\n");
+        assert_eq!(render(&jobs, true), "digraph plan {\n    rankdir=LR;\n    j0 [label=\"say \\\"hi\\\" (prod)\", shape=box];\n}\n");
+    }
+}
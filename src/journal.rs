@@ -0,0 +1,142 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_MAX_SIZE_MB: u64 = 10;
+const DEFAULT_MAX_AGE_DAYS: u64 = 30;
+const DEFAULT_KEEP: u32 = 5;
+
+// an append-only log of runs and their jobs as json lines, rotated to <path>.1, <path>.2, ...
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct JournalConfig {
+    path: String,
+    max_size_mb: Option<u64>,
+    max_age_days: Option<u64>,
+    // number of rotated files kept
+    keep: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct JobRecord<'a> {
+    pub job: &'a str,
+    pub instance: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<&'a str>,
+    pub message: &'a str,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    pub build_url: &'a str,
+    // seconds from the start of the run to the end of the job
+    pub duration: u64,
+    // seconds the build took as reported by jenkins, without the waits for gates, locks and the queue;
+    // none for a build the run didn't trigger itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_duration: Option<u64>,
+}
+
+// a job of a previous run, read back from the journal
+#[derive(Deserialize, Debug)]
+pub struct PreviousJob {
+    #[serde(rename = "type")]
+    kind: String,
+    run: u128,
+    pub job: String,
+    pub instance: String,
+    pub result: Option<String>,
+    pub build_duration: Option<u64>,
+}
+
+// a line of the journal, `run` is the start time of the run in milliseconds and identifies it
+#[derive(Serialize)]
+struct Line<'a, T: Serialize> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    run: u128,
+    #[serde(flatten)]
+    record: &'a T,
+}
+
+#[derive(Serialize)]
+pub struct RunRecord<'a> {
+    #[serde(skip)]
+    pub start: u128,
+    pub end: u128,
+    // who started the run, for the audit trail
+    pub user: String,
+    pub host: String,
+    pub args: Vec<String>,
+    pub total: usize,
+    pub success: usize,
+    pub exit_code: i32,
+    #[serde(skip)]
+    pub jobs: Vec<JobRecord<'a>>,
+}
+
+pub fn now_millis() -> u128 {
+    time::SystemTime::now().duration_since(time::UNIX_EPOCH).unwrap_or_default().as_millis()
+}
+
+impl JournalConfig {
+    fn rotate(&self) -> Result<()> {
+        let metadata = match fs::metadata(&self.path) {
+            Ok(v) => v,
+            Err(_) => return Ok(())
+        };
+        let max_size = self.max_size_mb.unwrap_or(DEFAULT_MAX_SIZE_MB) * 1024 * 1024;
+        let max_age = time::Duration::from_secs(self.max_age_days.unwrap_or(DEFAULT_MAX_AGE_DAYS) * 24 * 3600);
+        let created = metadata.created().or_else(|_| metadata.modified())?;
+        let age = time::SystemTime::now().duration_since(created).unwrap_or_default();
+        if metadata.len() < max_size && age < max_age {
+            return Ok(())
+        }
+        let keep = self.keep.unwrap_or(DEFAULT_KEEP);
+        for i in (1..keep).rev() {
+            let from = format!("{}.{}", &self.path, i);
+            if Path::new(&from).exists() {
+                fs::rename(&from, format!("{}.{}", &self.path, i + 1))?;
+            }
+        }
+        if keep > 0 {
+            fs::rename(&self.path, format!("{}.1", &self.path))?;
+        } else {
+            fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+
+    // the jobs of the last run in the journal, falling back to the last rotated file
+    pub fn last_run(&self) -> Result<Vec<PreviousJob>> {
+        for path in [self.path.clone(), format!("{}.1", &self.path)] {
+            let content = match fs::read_to_string(&path) {
+                Ok(v) => v,
+                Err(_) => continue
+            };
+            // lines of other versions or broken by a crash are skipped
+            let jobs: Vec<PreviousJob> = content.lines()
+                .filter_map(|line| serde_json::from_str::<PreviousJob>(line).ok())
+                .filter(|v| v.kind == "job").collect();
+            if let Some(last) = jobs.last().map(|v| v.run) {
+                return Ok(jobs.into_iter().filter(|v| v.run == last).collect())
+            }
+        }
+        Ok(Vec::new())
+    }
+
+    // appends one line per job and a final line for the run
+    pub fn write(&self, run: &RunRecord) -> Result<()> {
+        self.rotate().with_context(|| format!("Failed to rotate {:?}", &self.path))?;
+        let mut content = String::new();
+        for job in &run.jobs {
+            content += &(serde_json::to_string(&Line { kind: "job", run: run.start, record: job })? + "\n");
+        }
+        content += &(serde_json::to_string(&Line { kind: "run", run: run.start, record: run })? + "\n");
+        let mut f = OpenOptions::new().create(true).append(true).open(&self.path)
+            .with_context(|| format!("Failed to open {:?}", &self.path))?;
+        // a single write keeps the lines of concurrent runs from interleaving
+        f.write_all(content.as_bytes()).with_context(|| format!("Failed to write {:?}", &self.path))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,293 @@
+#[cfg(feature = "notify")]
+use std::collections::HashMap;
+use std::time;
+#[cfg(feature = "notify")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "notify")]
+use anyhow::{anyhow, Context};
+use anyhow::Result;
+use serde::Deserialize;
+
+use crate::AppContext;
+#[cfg(feature = "notify")]
+use crate::{expand_template, spawn_detached, NotifyOn};
+
+#[cfg(feature = "notify")]
+const DEFAULT_JOB_FINISHED: &str = "{job} -> {result} ({duration}s) {build_url}";
+#[cfg(feature = "notify")]
+const DEFAULT_JOB_STALLED: &str = "{job} is STALLED: running {elapsed}s, estimated {estimated}s, no console output {build_url}";
+#[cfg(feature = "notify")]
+const DEFAULT_JOB_SLOW: &str = "{job} is SLOW: running {elapsed}s, expected {expected}s {build_url}";
+#[cfg(feature = "notify")]
+const DEFAULT_RUN_FINISHED: &str = "{success}/{total} jobs succeeded, {failure} failed";
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct NotifyConfig {
+    #[cfg(feature = "notify")]
+    templates: Option<TemplatesConfig>,
+    #[cfg(feature = "notify")]
+    slack: Option<SlackConfig>,
+    #[cfg(feature = "notify")]
+    telegram: Option<TelegramConfig>,
+}
+
+// message templates shared by all backends, `{name}` is replaced by the value of the event variable `name`
+#[cfg(feature = "notify")]
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct TemplatesConfig {
+    job_finished: Option<String>,
+    job_stalled: Option<String>,
+    job_slow: Option<String>,
+    run_finished: Option<String>,
+}
+
+#[cfg(feature = "notify")]
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct SlackConfig {
+    webhook_url: String,
+}
+
+#[cfg(feature = "notify")]
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct TelegramConfig {
+    bot_token: String,
+    chat_id: String,
+    // events sent to the chat, defaults to all
+    events: Option<Vec<EventKind>>,
+}
+
+#[cfg(feature = "notify")]
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum EventKind {
+    JobSuccess,
+    JobFailure,
+    JobStalled,
+    JobSlow,
+    RunFinished,
+}
+
+#[cfg(feature = "notify")]
+impl TelegramConfig {
+    fn wants(&self, event: &Event) -> bool {
+        match &self.events {
+            Some(events) => events.contains(&event.kind()),
+            None => true
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "notify"), allow(dead_code))]
+pub enum Event<'a> {
+    JobFinished { job: &'a str, instance: &'a str, result: &'a str, success: bool, build_url: &'a str,
+        duration: time::Duration },
+    JobStalled { job: &'a str, instance: &'a str, build_url: &'a str, elapsed: i64, estimated: i64 },
+    // past 150% of expected_duration_minutes
+    JobSlow { job: &'a str, instance: &'a str, build_url: &'a str, elapsed: i64, expected: i64 },
+    RunFinished { total: usize, success: usize, failure: usize },
+}
+
+#[cfg(feature = "notify")]
+impl Event<'_> {
+    fn kind(&self) -> EventKind {
+        match self {
+            Event::JobFinished { success: true, .. } => EventKind::JobSuccess,
+            Event::JobFinished { .. } => EventKind::JobFailure,
+            Event::JobStalled { .. } => EventKind::JobStalled,
+            Event::JobSlow { .. } => EventKind::JobSlow,
+            Event::RunFinished { .. } => EventKind::RunFinished,
+        }
+    }
+
+    fn template(&self, templates: &TemplatesConfig) -> String {
+        let (custom, default) = match self {
+            Event::JobFinished { .. } => (&templates.job_finished, DEFAULT_JOB_FINISHED),
+            Event::JobStalled { .. } => (&templates.job_stalled, DEFAULT_JOB_STALLED),
+            Event::JobSlow { .. } => (&templates.job_slow, DEFAULT_JOB_SLOW),
+            Event::RunFinished { .. } => (&templates.run_finished, DEFAULT_RUN_FINISHED),
+        };
+        custom.as_deref().unwrap_or(default).to_string()
+    }
+
+    // variables available to the templates, the ones from [variables] and `--var` are included too
+    fn variables(&self, ctx: &AppContext) -> HashMap<String, String> {
+        let mut variables = ctx.variables.clone();
+        let mut set = |k: &str, v: String| { variables.insert(k.to_string(), v); };
+        match self {
+            Event::JobFinished { job, instance, result, build_url, duration, .. } => {
+                set("job", job.to_string());
+                set("instance", instance.to_string());
+                set("result", result.to_string());
+                set("build_url", build_url.to_string());
+                set("duration", duration.as_secs().to_string());
+            }
+            Event::JobStalled { job, instance, build_url, elapsed, estimated } => {
+                set("job", job.to_string());
+                set("instance", instance.to_string());
+                set("build_url", build_url.to_string());
+                set("elapsed", (elapsed / 1000).to_string());
+                set("estimated", (estimated / 1000).to_string());
+            }
+            Event::JobSlow { job, instance, build_url, elapsed, expected } => {
+                set("job", job.to_string());
+                set("instance", instance.to_string());
+                set("build_url", build_url.to_string());
+                set("elapsed", (elapsed / 1000).to_string());
+                set("expected", (expected / 1000).to_string());
+            }
+            Event::RunFinished { total, success, failure } => {
+                set("total", total.to_string());
+                set("success", success.to_string());
+                set("failure", failure.to_string());
+            }
+        }
+        variables
+    }
+
+    fn render(&self, ctx: &AppContext, templates: &TemplatesConfig) -> Result<String> {
+        let message = expand_template(&self.template(templates), &self.variables(ctx))
+            .context("Failed to render notification template")?;
+        Ok(ctx.redact(&message))
+    }
+}
+
+// the state of the notifications during a run
+#[cfg(feature = "notify")]
+#[derive(Debug)]
+pub struct Notifier {
+    client: reqwest::Client,
+    first_failure_notified: AtomicBool,
+}
+
+#[cfg(feature = "notify")]
+impl Notifier {
+    pub fn new() -> Self {
+        Notifier {
+            client: reqwest::Client::builder().timeout(time::Duration::from_secs(10)).build().unwrap_or_default(),
+            first_failure_notified: AtomicBool::new(false),
+        }
+    }
+
+    // only the first failure and the end of the run pop up, everything else would be noise
+    fn wants_desktop(&self, ctx: &AppContext, event: &Event) -> bool {
+        if !ctx.args.notify_desktop {
+            return false
+        }
+        match event.kind() {
+            EventKind::RunFinished => ctx.config.jenkins.notifies_on(NotifyOn::Finish),
+            EventKind::JobFailure => !self.first_failure_notified.swap(true, Ordering::SeqCst)
+                && ctx.config.jenkins.notifies_on(NotifyOn::Failure),
+            _ => false
+        }
+    }
+}
+
+#[cfg(all(feature = "notify", target_os = "linux"))]
+fn desktop_command(message: &str) -> std::process::Command {
+    let mut cmd = std::process::Command::new("notify-send");
+    cmd.arg("jenkins-build").arg(message);
+    cmd
+}
+
+#[cfg(all(feature = "notify", target_os = "macos"))]
+fn desktop_command(message: &str) -> std::process::Command {
+    let mut cmd = std::process::Command::new("osascript");
+    cmd.arg("-e").arg("on run argv\ndisplay notification (item 1 of argv) with title \"jenkins-build\"\nend run")
+        .arg(message);
+    cmd
+}
+
+#[cfg(all(feature = "notify", windows))]
+fn desktop_command(message: &str) -> std::process::Command {
+    let script = "Add-Type -AssemblyName System.Windows.Forms; \
+        $n = New-Object System.Windows.Forms.NotifyIcon; \
+        $n.Icon = [System.Drawing.SystemIcons]::Information; $n.Visible = $true; \
+        $n.ShowBalloonTip(10000, 'jenkins-build', $env:JB_MESSAGE, 'Info'); Start-Sleep 10; $n.Dispose()";
+    let mut cmd = std::process::Command::new("powershell");
+    cmd.arg("-NoProfile").arg("-Command").arg(script).env("JB_MESSAGE", message);
+    cmd
+}
+
+#[cfg(all(feature = "notify", not(any(target_os = "linux", target_os = "macos", windows))))]
+fn desktop_command(message: &str) -> std::process::Command {
+    let mut cmd = std::process::Command::new("notify-send");
+    cmd.arg("jenkins-build").arg(message);
+    cmd
+}
+
+#[cfg(feature = "notify")]
+fn notify_desktop(message: &str) -> Result<()> {
+    let mut cmd = desktop_command(message);
+    cmd.stdout(std::process::Stdio::null()).stderr(std::process::Stdio::null());
+    // the windows balloon stays alive for a while, don't wait for it
+    spawn_detached(&mut cmd).context("Failed to show the desktop notification")
+}
+
+// sends the event to every configured backend, a notification must never break the release,
+// so failures are only returned for the caller to display
+#[cfg(feature = "notify")]
+pub async fn notify(ctx: &AppContext, event: Event<'_>) -> Result<()> {
+    let default = NotifyConfig::default();
+    let config = ctx.config.notify.as_ref().unwrap_or(&default);
+    let notifier = &ctx.notifier;
+    let telegram = config.telegram.as_ref().filter(|v| v.wants(&event));
+    let desktop = notifier.wants_desktop(ctx, &event);
+    if config.slack.is_none() && telegram.is_none() && !desktop {
+        return Ok(())
+    }
+    let message = event.render(ctx, config.templates.as_ref().unwrap_or(&TemplatesConfig::default()))?;
+    if desktop {
+        notify_desktop(&message)?;
+    }
+    if let Some(slack) = &config.slack {
+        notifier.client.post(&slack.webhook_url).json(&serde_json::json!({"text": message}))
+            .send().await.and_then(|r| r.error_for_status())
+            .map_err(|e| anyhow!("Failed to send the slack notification: {}", e.without_url()))?;
+    }
+    if let Some(telegram) = telegram {
+        let url = format!("https://api.telegram.org/bot{}/sendMessage", telegram.bot_token);
+        // the bot token is part of the url, keep it out of the error
+        notifier.client.post(&url).json(&serde_json::json!({"chat_id": telegram.chat_id, "text": message}))
+            .send().await.and_then(|r| r.error_for_status())
+            .map_err(|e| anyhow!("Failed to send the telegram notification: {}", e.without_url()))?;
+    }
+    Ok(())
+}
+
+// nothing is configured to send to
+#[cfg(not(feature = "notify"))]
+#[derive(Debug)]
+pub struct Notifier;
+
+#[cfg(not(feature = "notify"))]
+impl Notifier {
+    pub fn new() -> Self {
+        Notifier
+    }
+}
+
+#[cfg(not(feature = "notify"))]
+pub async fn notify(_ctx: &AppContext, _event: Event<'_>) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(all(test, feature = "notify"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn telegram_events_are_checked() {
+        let config: TelegramConfig = toml::from_str(
+            "bot_token = \"t\"\nchat_id = \"1\"\nevents = [\"job_failure\", \"run_finished\"]").unwrap();
+        assert_eq!(config.events, Some(vec![EventKind::JobFailure, EventKind::RunFinished]));
+        assert!(config.wants(&Event::RunFinished { total: 1, success: 0, failure: 1 }));
+        assert!(!config.wants(&Event::JobSlow { job: "api", instance: "dev", build_url: "", elapsed: 1, expected: 1 }));
+        let e = toml::from_str::<TelegramConfig>("bot_token = \"t\"\nchat_id = \"1\"\nevents = [\"job_failed\"]")
+            .unwrap_err();
+        assert!(e.to_string().contains("job_failed"), "{}", e);
+    }
+}
@@ -0,0 +1,431 @@
+use anyhow::{anyhow, Result};
+
+// the part of the regex syntax the failure rules need: literals, `.`, classes like `[a-z_]` or `[^0-9]`,
+// `\d \w \s` and escaped characters, groups, `|`, the greedy `* + ?` and the anchors `^ $`; a leading `(?i)`
+// ignores the case. The pattern is compiled to a Thompson NFA whose states are all followed at once along
+// the line, in time linear in its length and without recursion, whatever the line or the pattern
+pub struct Pattern {
+    program: Vec<Inst>,
+    ignore_case: bool,
+}
+
+type Alt = Vec<Vec<Piece>>;
+
+struct Piece {
+    node: Node,
+    min: usize,
+    // none is unbounded
+    max: Option<usize>,
+}
+
+enum Node {
+    Char(char),
+    Any,
+    Class(Vec<ClassItem>, bool),
+    Group(Alt),
+    Start,
+    End,
+}
+
+#[derive(Clone)]
+enum ClassItem {
+    Range(char, char),
+    Digit,
+    Word,
+    Space,
+}
+
+impl ClassItem {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            ClassItem::Range(a, b) => *a <= c && c <= *b,
+            ClassItem::Digit => c.is_ascii_digit(),
+            ClassItem::Word => c.is_alphanumeric() || c == '_',
+            ClassItem::Space => c.is_whitespace(),
+        }
+    }
+}
+
+// the states of the automaton, the first three consume a character
+enum Inst {
+    Char(char),
+    Any,
+    Class(Vec<ClassItem>, bool),
+    Start,
+    End,
+    // both states are followed
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Result<Pattern> {
+        let (ignore_case, rest) = match pattern.strip_prefix("(?i)") {
+            Some(v) => (true, v),
+            None => (false, pattern)
+        };
+        let mut parser = Parser { chars: rest.chars().collect(), pos: 0, ignore_case };
+        let alt = parser.alt()?;
+        if parser.pos < parser.chars.len() {
+            return Err(anyhow!("Unmatched `)` in {:?}", pattern))
+        }
+        let mut program = Vec::new();
+        compile_alt(&alt, &mut program);
+        program.push(Inst::Match);
+        Ok(Pattern { program, ignore_case })
+    }
+
+    pub fn is_match(&self, line: &str) -> bool {
+        let chars: Vec<char> = match self.ignore_case {
+            true => line.chars().map(fold).collect(),
+            false => line.chars().collect()
+        };
+        let mut threads = Threads {
+            current: Vec::new(),
+            next: Vec::new(),
+            // the position each state was last added at, a state is followed once per position
+            added: vec![usize::MAX; self.program.len()],
+            stack: Vec::new(),
+        };
+        for pos in 0..=chars.len() {
+            // a match may start at any position
+            if self.add(&mut threads, 0, pos, &chars, false) {
+                return true
+            }
+            if pos == chars.len() {
+                break
+            }
+            for idx in 0..threads.current.len() {
+                let pc = threads.current[idx];
+                let matched = match &self.program[pc] {
+                    Inst::Char(c) => chars[pos] == *c,
+                    Inst::Any => true,
+                    Inst::Class(items, negated) => self.class_matches(items, chars[pos]) != *negated,
+                    _ => false
+                };
+                if matched && self.add(&mut threads, pc + 1, pos + 1, &chars, true) {
+                    return true
+                }
+            }
+            std::mem::swap(&mut threads.current, &mut threads.next);
+            threads.next.clear();
+        }
+        false
+    }
+
+    // follows the states reached without consuming a character, true once the pattern matched
+    fn add(&self, threads: &mut Threads, pc: usize, pos: usize, text: &[char], next: bool) -> bool {
+        threads.stack.push(pc);
+        while let Some(pc) = threads.stack.pop() {
+            if threads.added[pc] == pos {
+                continue
+            }
+            threads.added[pc] = pos;
+            match self.program[pc] {
+                Inst::Jump(to) => threads.stack.push(to),
+                Inst::Split(a, b) => {
+                    threads.stack.push(b);
+                    threads.stack.push(a);
+                }
+                Inst::Start if pos == 0 => threads.stack.push(pc + 1),
+                Inst::End if pos == text.len() => threads.stack.push(pc + 1),
+                Inst::Start | Inst::End => {}
+                Inst::Match => {
+                    threads.stack.clear();
+                    return true
+                }
+                _ => match next {
+                    true => threads.next.push(pc),
+                    false => threads.current.push(pc)
+                }
+            }
+        }
+        false
+    }
+
+    fn class_matches(&self, items: &[ClassItem], c: char) -> bool {
+        // the text is folded already, `[A-Z]` also has to match the folded characters
+        items.iter().any(|v| v.matches(c) || (self.ignore_case && v.matches(c.to_ascii_uppercase())))
+    }
+}
+
+struct Threads {
+    // the states waiting for the character at the position, and for the next one
+    current: Vec<usize>,
+    next: Vec<usize>,
+    added: Vec<usize>,
+    stack: Vec<usize>,
+}
+
+fn compile_alt(alt: &Alt, program: &mut Vec<Inst>) {
+    let mut jumps = Vec::new();
+    for (idx, seq) in alt.iter().enumerate() {
+        let split = program.len();
+        let last = idx + 1 == alt.len();
+        if !last {
+            program.push(Inst::Split(split + 1, 0));
+        }
+        for piece in seq {
+            compile_piece(piece, program);
+        }
+        if !last {
+            jumps.push(program.len());
+            program.push(Inst::Jump(0));
+            program[split] = Inst::Split(split + 1, program.len());
+        }
+    }
+    for idx in jumps {
+        program[idx] = Inst::Jump(program.len());
+    }
+}
+
+fn compile_piece(piece: &Piece, program: &mut Vec<Inst>) {
+    for _ in 0..piece.min {
+        compile_node(&piece.node, program);
+    }
+    match piece.max {
+        None => {
+            let split = program.len();
+            program.push(Inst::Split(split + 1, 0));
+            compile_node(&piece.node, program);
+            program.push(Inst::Jump(split));
+            program[split] = Inst::Split(split + 1, program.len());
+        }
+        Some(max) => for _ in piece.min..max {
+            let split = program.len();
+            program.push(Inst::Split(split + 1, 0));
+            compile_node(&piece.node, program);
+            program[split] = Inst::Split(split + 1, program.len());
+        }
+    }
+}
+
+fn compile_node(node: &Node, program: &mut Vec<Inst>) {
+    match node {
+        Node::Char(c) => program.push(Inst::Char(*c)),
+        Node::Any => program.push(Inst::Any),
+        Node::Class(items, negated) => program.push(Inst::Class(items.clone(), *negated)),
+        Node::Group(alt) => compile_alt(alt, program),
+        Node::Start => program.push(Inst::Start),
+        Node::End => program.push(Inst::End),
+    }
+}
+
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    ignore_case: bool,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn alt(&mut self) -> Result<Alt> {
+        let mut alt = vec![self.seq()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            alt.push(self.seq()?);
+        }
+        Ok(alt)
+    }
+
+    fn seq(&mut self) -> Result<Vec<Piece>> {
+        let mut seq = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break
+            }
+            let node = self.node()?;
+            let (min, max) = match self.peek() {
+                Some('*') => (0, None),
+                Some('+') => (1, None),
+                Some('?') => (0, Some(1)),
+                _ => (1, Some(1))
+            };
+            if max != Some(1) || min == 0 {
+                self.pos += 1;
+                if matches!(node, Node::Start | Node::End) {
+                    return Err(anyhow!("Nothing to repeat at {}", self.pos))
+                }
+            }
+            seq.push(Piece { node, min, max });
+        }
+        Ok(seq)
+    }
+
+    fn node(&mut self) -> Result<Node> {
+        let c = self.next().unwrap_or_default();
+        Ok(match c {
+            '(' => {
+                // `(?:` is the same as a plain group here, nothing is captured
+                if self.chars[self.pos..].starts_with(&['?', ':']) {
+                    self.pos += 2;
+                }
+                let alt = self.alt()?;
+                if self.next() != Some(')') {
+                    return Err(anyhow!("Unclosed `(`"))
+                }
+                Node::Group(alt)
+            }
+            '[' => self.class()?,
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '*' | '+' | '?' => return Err(anyhow!("Nothing to repeat at {}", self.pos)),
+            '\\' => match self.escape()? {
+                Escape::Char(c) => self.char(c),
+                Escape::Class(item, negated) => Node::Class(vec![item], negated)
+            },
+            c => self.char(c)
+        })
+    }
+
+    fn char(&self, c: char) -> Node {
+        Node::Char(if self.ignore_case { fold(c) } else { c })
+    }
+
+    fn escape(&mut self) -> Result<Escape> {
+        let c = self.next().ok_or_else(|| anyhow!("Trailing `\\`"))?;
+        Ok(match c {
+            'd' => Escape::Class(ClassItem::Digit, false),
+            'D' => Escape::Class(ClassItem::Digit, true),
+            'w' => Escape::Class(ClassItem::Word, false),
+            'W' => Escape::Class(ClassItem::Word, true),
+            's' => Escape::Class(ClassItem::Space, false),
+            'S' => Escape::Class(ClassItem::Space, true),
+            't' => Escape::Char('\t'),
+            c => Escape::Char(c)
+        })
+    }
+
+    fn class(&mut self) -> Result<Node> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+        let mut items = Vec::new();
+        loop {
+            let c = self.next().ok_or_else(|| anyhow!("Unclosed `[`"))?;
+            // a `]` right after the opening one is a literal
+            if c == ']' && !items.is_empty() {
+                break
+            }
+            let from = match c {
+                '\\' => match self.escape()? {
+                    Escape::Char(c) => c,
+                    Escape::Class(item, false) => {
+                        items.push(item);
+                        continue
+                    }
+                    Escape::Class(_, true) => return Err(anyhow!("Negated classes like `\\D` aren't supported inside `[]`"))
+                },
+                c => c
+            };
+            let to = match (self.peek(), self.chars.get(self.pos + 1)) {
+                (Some('-'), Some(v)) if *v != ']' => {
+                    self.pos += 2;
+                    *v
+                }
+                _ => from
+            };
+            items.push(ClassItem::Range(from, to));
+        }
+        Ok(Node::Class(items, negated))
+    }
+}
+
+enum Escape {
+    Char(char),
+    Class(ClassItem, bool),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_match(pattern: &str, line: &str) -> bool {
+        Pattern::new(pattern).unwrap().is_match(line)
+    }
+
+    #[test]
+    fn syntax() {
+        assert!(is_match("timed out", "connection timed out after 30s"));
+        assert!(!is_match("timed out", "connection timed  out"));
+        assert!(is_match("a.c", "xabcx"));
+        assert!(!is_match("a.c", "ac"));
+        assert!(is_match("^ERROR: [a-z_]+$", "ERROR: no_space"));
+        assert!(!is_match("^ERROR: [a-z_]+$", "ERROR: no space"));
+        assert!(!is_match("^ERROR", " ERROR"));
+        assert!(is_match("exit code [^0]", "exit code 2"));
+        assert!(!is_match("exit code [^0]", "exit code 0"));
+        assert!(is_match(r"\d+ tests? failed", "12 tests failed"));
+        assert!(is_match(r"\d+ tests? failed", "1 test failed"));
+        assert!(!is_match(r"\d+ tests? failed", "no tests failed"));
+        assert!(is_match(r"\w+\s\W", "foo_1 !"));
+        assert!(is_match(r"\D\S", "a1"));
+        assert!(!is_match(r"^\D\S$", "1a"));
+        assert!(is_match(r"\(1\.0\)", "version (1.0)"));
+        assert!(!is_match(r"\(1\.0\)", "version (100)"));
+        assert!(is_match(r"[\d.]+", "."));
+        assert!(is_match("[]a]", "]"));
+        assert!(is_match("[a-]", "-"));
+        assert!(is_match(r"a\tb", "a\tb"));
+    }
+
+    #[test]
+    fn groups_and_alternatives() {
+        assert!(is_match("(OOM|OutOfMemory)Error", "java.lang.OutOfMemoryError"));
+        assert!(is_match("(?:OOM|OutOfMemory)Error", "OOMError"));
+        assert!(!is_match("(OOM|OutOfMemory)Error", "MemoryError"));
+        assert!(is_match("^(ab)+$", "ababab"));
+        assert!(!is_match("^(ab)+$", "ababa"));
+        assert!(is_match("^(ab)*$", ""));
+        assert!(is_match("^a(b|)c$", "ac"));
+        assert!(is_match("^(a*)*$", "aaa"));
+        assert!(is_match("^x(a?)+y$", "xy"));
+        assert!(is_match("fatal|error", "an error"));
+        assert!(is_match("^fatal|error$", "fatal: no"));
+        assert!(!is_match("^fatal|error$", "no fatal error!"));
+    }
+
+    #[test]
+    fn ignore_case() {
+        assert!(is_match("(?i)connection refused", "Connection REFUSED"));
+        assert!(is_match("(?i)[A-Z]+ failed", "build failed"));
+        assert!(is_match("(?i)ÉCHEC", "échec"));
+        assert!(!is_match("connection refused", "Connection refused"));
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        for pattern in ["(a", "a)", "[a-z", "*a", "a|+", "^*", "a\\", "[\\D]"] {
+            assert!(Pattern::new(pattern).is_err(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn long_lines() {
+        let line = "a".repeat(20_000);
+        assert!(!is_match("(a|a)*b", &line));
+        assert!(!is_match("a*a*a*b", &line));
+        assert!(is_match("(a|a)*$", &line));
+        assert!(is_match("^(a+)+$", &line));
+        assert!(!is_match("^(a+)+b$", &line));
+        let line = line + " error";
+        assert!(is_match(".*error", &line));
+        assert!(is_match("^a+ (?:warning|error)$", &line));
+    }
+}
@@ -0,0 +1,176 @@
+use std::io::{stdin, stdout, IsTerminal, Write};
+use anyhow::{anyhow, Result};
+use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use crossterm::{cursor, terminal, QueueableCommand};
+
+const HELP: &str = "↑/↓ move, space toggle, a toggle all, / search, enter run the selected jobs, q cancel";
+const SEARCH_HELP: &str = "type to search, enter keep the matches, esc clear";
+
+// leaves raw mode however the picker ends
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        Ok(RawMode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+struct Picker<'a> {
+    items: &'a [String],
+    selected: Vec<bool>,
+    // fuzzy search of the items, typed after `/`
+    query: String,
+    searching: bool,
+    // the indexes of the items matching the query, best first
+    visible: Vec<usize>,
+    // in `visible`
+    cursor: usize,
+    // the first visible item, the list scrolls when it is taller than the terminal
+    top: usize,
+    height: usize,
+    // the lines above the cursor, cleared by the next draw
+    drawn: u16,
+}
+
+impl<'a> Picker<'a> {
+    fn filter(&mut self) {
+        self.visible = crate::fuzzy::rank(&self.query, self.items);
+        self.cursor = 0;
+    }
+
+    fn draw(&mut self) -> Result<()> {
+        if self.cursor < self.top {
+            self.top = self.cursor;
+        } else if self.cursor >= self.top + self.height {
+            self.top = self.cursor + 1 - self.height;
+        }
+        let mut buf: Vec<u8> = Vec::new();
+        if self.drawn > 0 {
+            buf.queue(cursor::MoveToPreviousLine(self.drawn))?;
+        }
+        buf.queue(cursor::MoveToColumn(0))?;
+        buf.queue(terminal::Clear(terminal::ClearType::FromCursorDown))?;
+        match (self.searching, self.query.is_empty()) {
+            (true, _) => write!(buf, "{}  /{}\r\n", SEARCH_HELP, self.query)?,
+            (false, false) => write!(buf, "{}  /{}\r\n", HELP, self.query)?,
+            (false, true) => write!(buf, "{}\r\n", HELP)?
+        }
+        let end = (self.top + self.height).min(self.visible.len());
+        for (position, idx) in self.visible.iter().enumerate().take(end).skip(self.top) {
+            let pointer = if position == self.cursor { ">" } else { " " };
+            let mark = if self.selected[*idx] { "[x]" } else { "[ ]" };
+            write!(buf, "{} {} {}\r\n", pointer, mark, self.items[*idx])?;
+        }
+        let count = self.selected.iter().filter(|v| **v).count();
+        write!(buf, "{}/{} selected", count, self.items.len())?;
+        self.drawn = (end.saturating_sub(self.top) + 1) as u16;
+        let mut out = stdout();
+        out.write_all(&buf)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<()> {
+        let mut out = stdout();
+        out.queue(cursor::MoveToPreviousLine(self.drawn))?;
+        out.queue(terminal::Clear(terminal::ClearType::FromCursorDown))?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+// a checkbox list of the items on the terminal, returns the indexes of the selected ones or None when cancelled
+pub fn pick(items: &[String]) -> Result<Option<Vec<usize>>> {
+    if !stdin().is_terminal() || !stdout().is_terminal() {
+        return Err(anyhow!("--interactive needs a terminal"))
+    }
+    if items.is_empty() {
+        return Ok(Some(Vec::new()))
+    }
+    // some terminals report no size
+    let rows = terminal::size().ok().map(|v| v.1 as usize).filter(|v| *v > 2).unwrap_or(24);
+    let mut picker = Picker {
+        items,
+        selected: vec![false; items.len()],
+        query: String::new(),
+        searching: false,
+        visible: (0..items.len()).collect(),
+        cursor: 0,
+        top: 0,
+        height: rows.saturating_sub(2).max(1),
+        drawn: 0,
+    };
+    let _raw = RawMode::enable()?;
+    loop {
+        picker.draw()?;
+        let key = match event::read()? {
+            Event::Key(v) => v,
+            _ => continue
+        };
+        let last = picker.visible.len().saturating_sub(1);
+        if picker.searching {
+            match key.code {
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    picker.clear()?;
+                    return Ok(None)
+                }
+                KeyCode::Char(c) => {
+                    picker.query.push(c);
+                    picker.filter();
+                }
+                KeyCode::Backspace => {
+                    picker.query.pop();
+                    picker.filter();
+                }
+                KeyCode::Enter => picker.searching = false,
+                KeyCode::Esc => {
+                    picker.searching = false;
+                    picker.query.clear();
+                    picker.filter();
+                }
+                KeyCode::Up => picker.cursor = picker.cursor.saturating_sub(1),
+                KeyCode::Down => picker.cursor = (picker.cursor + 1).min(last),
+                _ => {}
+            }
+            continue
+        }
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => picker.cursor = picker.cursor.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => picker.cursor = (picker.cursor + 1).min(last),
+            KeyCode::PageUp => picker.cursor = picker.cursor.saturating_sub(picker.height),
+            KeyCode::PageDown => picker.cursor = (picker.cursor + picker.height).min(last),
+            KeyCode::Char(' ') => {
+                if let Some(idx) = picker.visible.get(picker.cursor) {
+                    picker.selected[*idx] = !picker.selected[*idx];
+                }
+            }
+            // only the matches of the search
+            KeyCode::Char('a') => {
+                let all = picker.visible.iter().all(|v| picker.selected[*v]);
+                for idx in &picker.visible {
+                    picker.selected[*idx] = !all;
+                }
+            }
+            KeyCode::Char('/') => picker.searching = true,
+            KeyCode::Enter => break,
+            KeyCode::Esc | KeyCode::Char('q') => {
+                picker.clear()?;
+                return Ok(None)
+            }
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                picker.clear()?;
+                return Ok(None)
+            }
+            _ => {}
+        }
+    }
+    picker.clear()?;
+    Ok(Some(picker.selected.iter().enumerate().filter(|v| *v.1).map(|v| v.0).collect()))
+}
@@ -0,0 +1,61 @@
+use std::sync::Mutex;
+use std::time;
+use tokio::time::Instant;
+
+// the polls of every job of an instance share one schedule: a poller still polls once per interval,
+// but two requests to the instance are at least interval / pollers apart, so a hundred jobs started
+// together don't hit jenkins in the same second, every round
+#[derive(Default, Debug)]
+pub struct Scheduler {
+    state: Mutex<State>,
+}
+
+#[derive(Default, Debug)]
+struct State {
+    // when the last poll went out
+    last: Option<Instant>,
+    pollers: usize,
+}
+
+impl Scheduler {
+    // the first poll is due after an interval, like the sleep it replaces
+    pub fn poller(&self, interval: time::Duration) -> Poller<'_> {
+        self.state.lock().unwrap().pollers += 1;
+        Poller { scheduler: self, interval, due: Instant::now() + interval }
+    }
+
+    // the poll may go out now unless the last one is too recent, then it is retried at the returned time;
+    // the gap is taken from the pollers at that moment, the jobs of a run register over a few seconds
+    fn take_slot(&self, interval: time::Duration) -> Option<Instant> {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        let at = state.last.map(|v| v + interval / state.pollers.max(1) as u32).filter(|v| *v > now);
+        if at.is_none() {
+            state.last = Some(now);
+        }
+        at
+    }
+}
+
+pub struct Poller<'a> {
+    scheduler: &'a Scheduler,
+    interval: time::Duration,
+    due: Instant,
+}
+
+impl Poller<'_> {
+    // waits for the next poll to be due and for a free slot
+    pub async fn tick(&mut self) {
+        tokio::time::sleep_until(self.due).await;
+        while let Some(at) = self.scheduler.take_slot(self.interval) {
+            tokio::time::sleep_until(at).await;
+        }
+        self.due = Instant::now() + self.interval;
+    }
+}
+
+impl Drop for Poller<'_> {
+    fn drop(&mut self) {
+        self.scheduler.state.lock().unwrap().pollers -= 1;
+    }
+}
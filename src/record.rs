@@ -0,0 +1,78 @@
+use std::fs::File;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time;
+use serde::Serialize;
+
+use crate::AppContext;
+
+// bodies are cut to this many bytes, the interesting part of an error page is at the beginning
+const MAX_BODY_BYTES: usize = 2048;
+
+// one line of the `--record` file
+#[derive(Serialize)]
+struct Record<'a> {
+    time: u128,
+    // the X-Request-Id header sent
+    request_id: &'a str,
+    method: &'a str,
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    latency_ms: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+// the `--record` file, if any
+#[derive(Debug)]
+pub struct Recorder {
+    file: Option<Mutex<File>>,
+}
+
+impl Recorder {
+    pub fn new(path: Option<&str>) -> Self {
+        let file = path.and_then(|path| match File::create(path) {
+            Ok(f) => Some(Mutex::new(f)),
+            Err(e) => {
+                eprintln!("Failed to create {:?}: {:?}", path, e);
+                None
+            }
+        });
+        Recorder { file }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.file.is_some()
+    }
+}
+
+// appends a request to the record file, `result` is either the status and body or the error
+pub fn record(ctx: &AppContext, request_id: &str, method: &str, url: &str, latency: time::Duration, result: Result<(u16, &[u8]), String>) {
+    let file = match &ctx.recorder.file {
+        Some(v) => v,
+        None => return
+    };
+    let (status, body, error) = match result {
+        Ok((status, body)) => {
+            let body = String::from_utf8_lossy(&body[..body.len().min(MAX_BODY_BYTES)]);
+            (Some(status), Some(ctx.redact(&body)), None)
+        }
+        Err(e) => (None, None, Some(ctx.redact(&e)))
+    };
+    let record = Record {
+        time: time::SystemTime::now().duration_since(time::UNIX_EPOCH).unwrap_or_default().as_millis(),
+        request_id,
+        method,
+        url: ctx.redact(url),
+        status,
+        latency_ms: latency.as_millis(),
+        body,
+        error,
+    };
+    if let (Ok(line), Ok(mut f)) = (serde_json::to_string(&record), file.lock()) {
+        let _ = writeln!(f, "{}", line);
+    }
+}
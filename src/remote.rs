@@ -0,0 +1,118 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time;
+use anyhow::{anyhow, Context, Result};
+use crate::{redact_urls, Args};
+
+pub fn is_url(path: &str) -> bool {
+    path.starts_with("https://") || path.starts_with("http://")
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|v| format!("{:02x}", v)).collect()
+}
+
+// ~/.cache/jenkins-build/config-<hash of the url>.toml
+fn cache_path(url: &str) -> Option<PathBuf> {
+    let dir = match std::env::var("XDG_CACHE_HOME").ok().filter(|v| !v.is_empty()) {
+        Some(v) => PathBuf::from(v),
+        None => PathBuf::from(std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")).ok()?).join(".cache")
+    };
+    Some(dir.join("jenkins-build").join(format!("config-{}.toml", &hex(&openssl::sha::sha256(url.as_bytes()))[..16])))
+}
+
+fn verify(args: &Args, content: &str) -> Result<()> {
+    if let Some(expected) = &args.config_sha256 {
+        let actual = hex(&openssl::sha::sha256(content.as_bytes()));
+        if !actual.eq_ignore_ascii_case(expected.trim()) {
+            return Err(anyhow!("Checksum mismatch of the config: expected sha256 {}, got {}", expected, actual))
+        }
+    }
+    Ok(())
+}
+
+// the config holds the passwords of the instances, only the user may read the copy; written to a temporary
+// file first, a run never reads a partial copy
+fn write_cache(path: &Path, content: &str) -> std::io::Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+        builder.mode(0o700);
+        builder.create(dir)?;
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+    }
+    #[cfg(not(unix))]
+    builder.create(dir)?;
+    let tmp = path.with_extension("tmp");
+    let _ = fs::remove_file(&tmp);
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(&tmp)?.write_all(content.as_bytes())?;
+    fs::rename(&tmp, path)
+}
+
+async fn download(args: &Args, url: &str) -> Result<String> {
+    let mut request = reqwest::Client::builder().timeout(time::Duration::from_secs(10)).build()?.get(url);
+    if let Some(header) = &args.config_header {
+        let (name, value) = header.split_once(':').context("Invalid --config-header, expected `Name: value`")?;
+        request = request.header(name.trim(), value.trim());
+    }
+    // the url may hold credentials, keep it out of the reqwest errors
+    let content = request.send().await.and_then(|r| r.error_for_status())
+        .map_err(|e| anyhow!("Failed to get {:?}: {}", redact_urls(url), e.without_url()))?
+        .text().await.map_err(|e| anyhow!("Failed to read {:?}: {}", redact_urls(url), e.without_url()))?;
+    Ok(content)
+}
+
+// fetches the config given as a url, the last good copy is cached and used when the server can't be reached
+pub async fn fetch(args: &Args, url: &str) -> Result<String> {
+    let cache = cache_path(url);
+    match download(args, url).await {
+        Ok(v) => {
+            verify(args, &v)?;
+            if let Some(path) = &cache {
+                if let Err(e) = write_cache(path, &v) {
+                    eprintln!("Failed to cache the config in {:?}: {}", path, e);
+                }
+            }
+            Ok(v)
+        }
+        Err(e) => {
+            let cached = match cache.as_ref().and_then(|v| fs::read_to_string(v).ok()) {
+                Some(v) => v,
+                None => return Err(e)
+            };
+            verify(args, &cached)?;
+            eprintln!("Failed to fetch the config, using the cached copy {:?}: {:#}", cache.unwrap_or_default(), e);
+            Ok(cached)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_is_private() {
+        let dir = std::env::temp_dir().join(format!("jenkins-build-cache-{}", std::process::id()));
+        let path = dir.join("jenkins-build").join("config.toml");
+        write_cache(&path, "a = 1").unwrap();
+        write_cache(&path, "a = 2").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a = 2");
+        assert!(!path.with_extension("tmp").exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+            assert_eq!(fs::metadata(path.parent().unwrap()).unwrap().permissions().mode() & 0o777, 0o700);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
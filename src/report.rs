@@ -0,0 +1,664 @@
+#[cfg(feature = "tui")]
+use std::collections::HashMap;
+#[cfg(feature = "tui")]
+use std::env;
+use std::io::{stdout, IsTerminal, Write};
+#[cfg(feature = "tui")]
+use std::io::Stdout;
+use std::{fs, process, time};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+#[cfg(feature = "tui")]
+use crossterm::{cursor, terminal, QueueableCommand};
+#[cfg(feature = "tui")]
+use crossterm::style::{Color, Stylize};
+
+use crate::events::JobTimings;
+use crate::RunState;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    // the jobs redrawn in place, or a counts line with `--compact`
+    Live,
+    // a line per change without escape sequences, for CI logs
+    Plain,
+    // a json object per event on stdout
+    Json,
+}
+
+// `--group-by`, the live display puts a header line before the jobs of each group
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    // in the order the instances first appear in the job file
+    Instance,
+    // the jobs up to a manual gate, then the ones up to the next gate
+    Stage,
+}
+
+// displays the progress of the run, the state is owned by exec and only read here
+pub trait Reporter {
+    fn on_start(&mut self, _state: &RunState) {}
+    // the build of the job was created
+    fn on_triggered(&mut self, state: &RunState, idx: usize);
+    // an intermediate status like STALLED, empty once the job is running normally again
+    fn on_progress(&mut self, state: &RunState, idx: usize);
+    // may be called again for the same job with extra information about hooks and notifications
+    fn on_finished(&mut self, state: &RunState, idx: usize);
+    // draws the changes of a burst of events at once, see BATCH_WINDOW
+    fn flush(&mut self, _state: &RunState) {}
+    fn on_summary(&mut self, state: &RunState);
+    // the jobs before the gate are done, `gate..end` wait for the operator
+    fn on_gate(&mut self, state: &RunState, gate: usize, end: usize) {
+        for line in gate_lines(state, gate, end) {
+            println!("{}", line);
+        }
+    }
+    // the job failed, its rollback job is triggered if the operator agrees
+    fn on_rollback(&mut self, state: &RunState, idx: usize) {
+        for line in rollback_lines(state, idx) {
+            println!("{}", line);
+        }
+    }
+    // the canary build soaked, the full rollout is triggered if the operator promotes it
+    fn on_promotion(&mut self, state: &RunState, idx: usize, build_url: &str) {
+        for line in promotion_lines(state, idx, build_url) {
+            println!("{}", line);
+        }
+    }
+    // `--timings`, after the summary
+    fn on_timings(&mut self, state: &RunState) {
+        for line in timings_chart(state) {
+            println!("{}", line);
+        }
+    }
+}
+
+// events arriving within this window after the first one are drawn together
+pub const BATCH_WINDOW: time::Duration = time::Duration::from_millis(50);
+
+// live on a terminal, plain otherwise, always plain without the tui feature
+#[cfg_attr(not(feature = "tui"), allow(unused_variables))]
+pub fn new(output: Option<Output>, compact: bool, group_by: Option<GroupBy>, sort_by_status: bool) -> Box<dyn Reporter> {
+    let output = output.unwrap_or(if stdout().is_terminal() { Output::Live } else { Output::Plain });
+    match output {
+        #[cfg(feature = "tui")]
+        Output::Live if live_supported() => Box::new(LiveReporter::new(compact, group_by, sort_by_status)),
+        Output::Live => Box::new(PlainReporter),
+        Output::Plain => Box::new(PlainReporter),
+        Output::Json => Box::new(JsonReporter { out: Box::new(stdout()) }),
+    }
+}
+
+// `--renderer-cmd`, the json events go to the stdin of the command, which draws the run instead
+pub fn external(command: &str) -> Result<Box<dyn Reporter>> {
+    let mut child = crate::shell_command(command).stdin(process::Stdio::piped()).spawn()
+        .with_context(|| format!("Failed to run the renderer {:?}", command))?;
+    let stdin = child.stdin.take().context("No stdin for the renderer")?;
+    Ok(Box::new(ExternalReporter { json: JsonReporter { out: Box::new(stdin) }, child }))
+}
+
+// older windows consoles only move the cursor once virtual terminal processing is enabled, which
+// supports_ansi tries, the redraws would leave artifacts otherwise
+#[cfg(all(feature = "tui", windows))]
+fn live_supported() -> bool {
+    crossterm::ansi_support::supports_ansi()
+}
+
+#[cfg(all(feature = "tui", not(windows)))]
+fn live_supported() -> bool {
+    true
+}
+
+// whether the terminal renders OSC 8 hyperlinks, FORCE_HYPERLINK=0/1 overrides the detection
+#[cfg(feature = "tui")]
+fn supports_hyperlinks() -> bool {
+    if let Ok(v) = env::var("FORCE_HYPERLINK") {
+        return v != "0"
+    }
+    if !stdout().is_terminal() {
+        return false
+    }
+    if env::var("WT_SESSION").is_ok() || env::var("KONSOLE_VERSION").is_ok() {
+        return true
+    }
+    if let Ok(v) = env::var("VTE_VERSION") {
+        return v.parse::<u32>().unwrap_or_default() >= 5000
+    }
+    if let Ok(v) = env::var("TERM_PROGRAM") {
+        return matches!(v.as_str(), "iTerm.app" | "WezTerm" | "vscode" | "ghostty")
+    }
+    let term = env::var("TERM").unwrap_or_default();
+    term.contains("kitty") || term.contains("alacritty") || term.contains("foot")
+}
+
+// like `3/12 done, 1 failed`, for the title of the terminal and `--progress-file`
+pub fn progress(state: &RunState) -> String {
+    let done = state.finished.iter().filter(|v| **v).count();
+    format!("{}/{} done, {} failed", done, state.jobs.len(), done.saturating_sub(state.succeeded()))
+}
+
+// the footer of the live display, like `7/15 finished · 1 failed · elapsed 06:12`
+#[cfg(feature = "tui")]
+fn totals(state: &RunState) -> String {
+    let done = state.finished.iter().filter(|v| **v).count();
+    let elapsed = state.start.elapsed().as_secs();
+    let elapsed = if elapsed >= 3600 {
+        format!("{}:{:02}:{:02}", elapsed / 3600, elapsed / 60 % 60, elapsed % 60)
+    } else {
+        format!("{:02}:{:02}", elapsed / 60, elapsed % 60)
+    };
+    format!("{}/{} finished · {} failed · elapsed {}", done, state.jobs.len(),
+            done.saturating_sub(state.succeeded()), elapsed)
+}
+
+// `--sort-by-status`, the failed jobs first, then the running ones and the succeeded ones last
+#[cfg(feature = "tui")]
+fn status_rank(state: &RunState, idx: usize) -> u8 {
+    if !state.finished[idx] {
+        return 1
+    }
+    match state.results[idx] {
+        Some(v) if state.jobs[idx].is_success(v) => 2,
+        _ => 0
+    }
+}
+
+// `--progress-file`, the progress for the status line of tmux or a prompt, removed when the run ends
+pub struct ProgressFile {
+    path: String,
+    last: String,
+}
+
+impl ProgressFile {
+    pub fn new(path: &str) -> Self {
+        ProgressFile { path: path.to_string(), last: String::new() }
+    }
+
+    // only written when the progress changed
+    pub fn update(&mut self, state: &RunState) {
+        let line = progress(state);
+        if line != self.last {
+            let _ = fs::write(&self.path, format!("{}\n", line));
+            self.last = line;
+        }
+    }
+}
+
+impl Drop for ProgressFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// the waits in the jenkins queue, with a warning when the average exceeds `queue_wait_warn_seconds`
+fn queue_summary(state: &RunState) -> Vec<String> {
+    let stats = match state.queue_stats() {
+        Some(v) => v,
+        None => return Vec::new()
+    };
+    let worst: Vec<String> = stats.worst.iter()
+        .map(|(idx, wait)| format!("{} {}s", state.jobs[*idx].label(), wait.as_secs())).collect();
+    let mut lines = vec![format!("queue wait: p50 {}s, p95 {}s, average {}s, longest {}",
+                                 stats.p50.as_secs(), stats.p95.as_secs(), stats.average.as_secs(), worst.join(", "))];
+    if let Some(threshold) = stats.exceeded {
+        lines.push(format!("warning: the builds waited {}s in the queue on average, more than {}s, \
+            the instances may be short of executors", stats.average.as_secs(), threshold.as_secs()));
+    }
+    lines
+}
+
+fn gate_lines(state: &RunState, gate: usize, end: usize) -> Vec<String> {
+    let succeeded = (0..gate).filter(|idx| state.results[*idx].map(|v| state.jobs[*idx].is_success(v)).unwrap_or_default())
+        .count();
+    let mut lines = vec![format!("manual gate: {}/{} jobs before succeeded, next:", succeeded, gate)];
+    for job in &state.jobs[gate..end] {
+        lines.push(format!("  {} ({})", job.label(), job.instance_name));
+    }
+    lines
+}
+
+fn rollback_lines(state: &RunState, idx: usize) -> Vec<String> {
+    let job = &state.jobs[idx];
+    vec![format!("{} failed: {}", job.label(), state.v[idx]),
+         format!("  its rollback job is {} ({})", job.rollback_job.as_deref().unwrap_or_default(), job.instance_name)]
+}
+
+fn promotion_lines(state: &RunState, idx: usize, build_url: &str) -> Vec<String> {
+    let job = &state.jobs[idx];
+    vec![format!("canary of {} passed: {}", job.label(), build_url),
+         format!("  next is the full rollout on {}", job.instance_name)]
+}
+
+const CHART_WIDTH: usize = 50;
+
+// the phases of every triggered job in a table, then on the timeline of the run:
+// `t` trigger, `q` queue, `#` build, `~` polling until the result was seen
+fn timings_chart(state: &RunState) -> Vec<String> {
+    let timed: Vec<(usize, &JobTimings)> = state.timings.iter().enumerate()
+        .filter_map(|(idx, v)| v.as_ref().map(|v| (idx, v))).collect();
+    if timed.is_empty() {
+        return vec!["no timings, no build was triggered by this run".to_string()]
+    }
+    let start = timed.iter().map(|v| v.1.triggered).min().unwrap_or(state.start);
+    let end = timed.iter().map(|v| v.1.triggered + v.1.total).max().unwrap_or(start);
+    let span = (end - start).as_secs_f64().max(0.001);
+    let column = |at: time::Duration| ((at.as_secs_f64() / span * CHART_WIDTH as f64).round() as usize).min(CHART_WIDTH);
+    let width = timed.iter().map(|v| state.jobs[v.0].label().chars().count()).max().unwrap_or_default();
+    let secs = |v: time::Duration| format!("{:.1}s", v.as_secs_f64());
+    let mut lines = vec![format!("{:width$}  {:>8} {:>8} {:>8} {:>8} {:>8}", "job", "trigger", "queue", "build",
+                                 "polling", "total", width = width)];
+    for (idx, v) in &timed {
+        lines.push(format!("{:width$}  {:>8} {:>8} {:>8} {:>8} {:>8}", state.jobs[*idx].label(), secs(v.trigger),
+                           secs(v.queue), secs(v.build), secs(v.polling), secs(v.total), width = width));
+    }
+    lines.push(String::new());
+    for (idx, v) in &timed {
+        let mut bar = vec![' '; CHART_WIDTH];
+        let mut at = v.triggered - start;
+        for (phase, c) in [(v.trigger, 't'), (v.queue, 'q'), (v.build, '#'), (v.polling, '~')] {
+            let from = column(at);
+            at += phase;
+            if phase.is_zero() {
+                continue
+            }
+            for cell in bar.iter_mut().take(column(at).max(from + 1).min(CHART_WIDTH)).skip(from) {
+                *cell = c;
+            }
+        }
+        lines.push(format!("{:width$}  |{}|", state.jobs[*idx].label(), bar.into_iter().collect::<String>(), width = width));
+    }
+    lines.push(format!("{:width$}   0s{:>w$}", "", secs(end - start), width = width, w = CHART_WIDTH - 2));
+    lines
+}
+
+#[cfg(feature = "tui")]
+fn hyperlink(text: &str, url: &str) -> String {
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+}
+
+#[cfg(feature = "tui")]
+pub struct LiveReporter {
+    stdout: Stdout,
+    // the lines on the screen, only the ones that changed are redrawn
+    lines: Vec<String>,
+    // finished jobs printed above the counts line with `--compact`, by job, and the last status printed of
+    // each; the result of a job is sent again after a rollback or a failed hook, it replaces the pending line
+    // or follows the one already printed when it changed
+    finished_lines: Vec<(usize, String)>,
+    printed: HashMap<usize, String>,
+    dirty: bool,
+    hyperlinks: bool,
+    colors: bool,
+    compact: bool,
+    group_by: Option<GroupBy>,
+    sort_by_status: bool,
+    // the progress in the title of the terminal window, only set when it changed
+    title: String,
+}
+
+#[cfg(feature = "tui")]
+impl LiveReporter {
+    fn new(compact: bool, group_by: Option<GroupBy>, sort_by_status: bool) -> Self {
+        LiveReporter {
+            stdout: stdout(),
+            lines: Vec::new(),
+            finished_lines: Vec::new(),
+            printed: HashMap::new(),
+            dirty: false,
+            hyperlinks: supports_hyperlinks(),
+            colors: stdout().is_terminal() && env::var("NO_COLOR").is_err(),
+            compact,
+            group_by,
+            sort_by_status,
+            title: String::new(),
+        }
+    }
+
+    // links the job name to the build page and the result to its console
+    fn link(&self, state: &RunState, idx: usize, text: &str, path: &str) -> String {
+        if !self.hyperlinks || state.urls[idx].is_empty() {
+            return text.to_string()
+        }
+        hyperlink(text, &(state.urls[idx].clone() + path))
+    }
+
+    // the finished status, colored by the result and linked to the console
+    fn result_text(&self, state: &RunState, idx: usize) -> String {
+        let text = self.link(state, idx, &state.v[idx], "console");
+        match (self.colors, state.results[idx]) {
+            (true, Some(result)) => text.with(result.color()).to_string(),
+            (true, None) if state.finished[idx] => text.with(Color::Red).to_string(),
+            _ => text
+        }
+    }
+
+    fn render(&self, state: &RunState) -> Vec<String> {
+        if self.compact {
+            let running = state.finished.iter().filter(|v| !**v).count();
+            let success = state.succeeded();
+            let failed = state.jobs.len() - running - success;
+            return vec![format!("running {} | success {} | failed {}", running, success, failed)]
+        }
+        // only the rows move, the jobs keep their index in the state
+        let mut order: Vec<usize> = (0..state.jobs.len()).collect();
+        order.sort_by_key(|idx| (self.group(state, *idx), if self.sort_by_status { status_rank(state, *idx) } else { 0 }));
+        let mut lines = Vec::with_capacity(order.len() + 1);
+        let mut header = None;
+        for idx in order {
+            let group = self.group(state, idx);
+            if self.group_by.is_some() && header != Some(group) {
+                lines.push(self.header(state, idx, group));
+                header = Some(group);
+            }
+            let name = self.link(state, idx, &state.jobs[idx].label(), "");
+            if state.v[idx].is_empty() {
+                lines.push(format!("{} -> 发布中", name))
+            } else {
+                lines.push(format!("{} -> {}", name, self.result_text(state, idx)))
+            }
+        }
+        lines.push(totals(state));
+        lines
+    }
+
+    // the position of the group of the job, the first job of an instance or after a gate starts one
+    fn group(&self, state: &RunState, idx: usize) -> usize {
+        match self.group_by {
+            None => 0,
+            Some(GroupBy::Instance) => state.jobs.iter()
+                .position(|v| v.instance_name == state.jobs[idx].instance_name).unwrap_or_default(),
+            Some(GroupBy::Stage) => (1..=idx).filter(|v| state.jobs[*v].gate.is_some()).count(),
+        }
+    }
+
+    fn header(&self, state: &RunState, idx: usize, group: usize) -> String {
+        let header = match self.group_by {
+            Some(GroupBy::Stage) => format!("stage {}", group + 1),
+            _ => format!("[{}]", state.jobs[idx].instance_name),
+        };
+        if self.colors {
+            header.bold().to_string()
+        } else {
+            header
+        }
+    }
+
+    // the lines of the jobs are drawn again below the ones printed, and the prompt that follows
+    fn print_below(&mut self, state: &RunState, lines: Vec<String>) {
+        self.flush(state);
+        for line in lines {
+            println!("{}", line);
+        }
+        self.lines.clear();
+        self.dirty = true;
+    }
+
+    // the frame is built in a buffer and written at once, the cursor stays below the last line
+    fn draw(&mut self, state: &RunState) {
+        let lines = self.render(state);
+        let mut buf: Vec<u8> = Vec::new();
+        let previous = self.lines.len() as u16;
+        if !self.finished_lines.is_empty() {
+            // the finished jobs take the place of the counts line, which is drawn again below them
+            if previous > 0 {
+                let _ = buf.queue(cursor::MoveToPreviousLine(previous));
+                let _ = buf.queue(terminal::Clear(terminal::ClearType::FromCursorDown));
+            }
+            for (_, line) in self.finished_lines.drain(..) {
+                let _ = writeln!(buf, "{}", line);
+            }
+            self.lines.clear();
+        }
+        if self.lines.len() != lines.len() {
+            for line in &lines {
+                let _ = writeln!(buf, "{}", line);
+            }
+        } else {
+            let count = lines.len();
+            for (idx, line) in lines.iter().enumerate() {
+                if *line == self.lines[idx] {
+                    continue
+                }
+                let _ = buf.queue(cursor::MoveToPreviousLine((count - idx) as u16));
+                let _ = buf.queue(terminal::Clear(terminal::ClearType::CurrentLine));
+                let _ = write!(buf, "{}", line);
+                let _ = buf.queue(cursor::MoveToNextLine((count - idx) as u16));
+            }
+        }
+        let title = format!("jenkins-build: {}", progress(state));
+        if title != self.title {
+            let _ = buf.queue(terminal::SetTitle(&title));
+            self.title = title;
+        }
+        self.lines = lines;
+        self.dirty = false;
+        let _ = self.stdout.write_all(&buf);
+        let _ = self.stdout.flush();
+    }
+}
+
+#[cfg(feature = "tui")]
+impl Reporter for LiveReporter {
+    fn on_start(&mut self, state: &RunState) {
+        self.draw(state)
+    }
+
+    // the url only changes the links, they show up with the next redraw
+    fn on_triggered(&mut self, _state: &RunState, _idx: usize) {
+        self.dirty = true
+    }
+
+    // intermediate states like STALLED are only visible in the full display
+    fn on_progress(&mut self, _state: &RunState, _idx: usize) {
+        self.dirty = true
+    }
+
+    fn on_finished(&mut self, state: &RunState, idx: usize) {
+        if self.compact && self.printed.get(&idx) != Some(&state.v[idx]) {
+            let line = format!("{} -> {}", self.link(state, idx, &state.jobs[idx].label(), ""),
+                               self.result_text(state, idx));
+            match self.finished_lines.iter_mut().find(|v| v.0 == idx) {
+                Some(pending) => pending.1 = line,
+                None => self.finished_lines.push((idx, line)),
+            }
+            self.printed.insert(idx, state.v[idx].clone());
+        }
+        self.dirty = true
+    }
+
+    fn flush(&mut self, state: &RunState) {
+        if self.dirty {
+            self.draw(state)
+        }
+    }
+
+    fn on_gate(&mut self, state: &RunState, gate: usize, end: usize) {
+        self.print_below(state, gate_lines(state, gate, end))
+    }
+
+    fn on_rollback(&mut self, state: &RunState, idx: usize) {
+        self.print_below(state, rollback_lines(state, idx))
+    }
+
+    fn on_promotion(&mut self, state: &RunState, idx: usize, build_url: &str) {
+        self.print_below(state, promotion_lines(state, idx, build_url))
+    }
+
+    fn on_summary(&mut self, state: &RunState) {
+        self.flush(state);
+        for line in queue_summary(state) {
+            println!("{}", line);
+        }
+    }
+}
+
+pub struct PlainReporter;
+
+impl Reporter for PlainReporter {
+    fn on_triggered(&mut self, state: &RunState, idx: usize) {
+        println!("{} -> {}", state.jobs[idx].label(), state.urls[idx]);
+    }
+
+    fn on_progress(&mut self, state: &RunState, idx: usize) {
+        if !state.v[idx].is_empty() {
+            println!("{} -> {}", state.jobs[idx].label(), state.v[idx]);
+        }
+    }
+
+    fn on_finished(&mut self, state: &RunState, idx: usize) {
+        println!("{} -> {}", state.jobs[idx].label(), state.v[idx]);
+    }
+
+    fn on_summary(&mut self, state: &RunState) {
+        println!("{}/{} jobs succeeded", state.succeeded(), state.jobs.len());
+        for line in queue_summary(state) {
+            println!("{}", line);
+        }
+    }
+}
+
+pub struct JsonReporter {
+    out: Box<dyn Write>,
+}
+
+impl JsonReporter {
+    // a renderer that went away doesn't stop the run
+    fn emit(&mut self, value: serde_json::Value) {
+        let _ = writeln!(self.out, "{}", value).and_then(|_| self.out.flush());
+    }
+}
+
+impl Reporter for JsonReporter {
+    fn on_triggered(&mut self, state: &RunState, idx: usize) {
+        let job = &state.jobs[idx];
+        self.emit(serde_json::json!({"event": "triggered", "job": job.name, "label": job.label(),
+            "instance": job.instance_name, "build_url": state.urls[idx]}));
+    }
+
+    fn on_progress(&mut self, state: &RunState, idx: usize) {
+        let job = &state.jobs[idx];
+        self.emit(serde_json::json!({"event": "progress", "job": job.name, "label": job.label(),
+            "instance": job.instance_name, "status": state.v[idx]}));
+    }
+
+    fn on_finished(&mut self, state: &RunState, idx: usize) {
+        let job = &state.jobs[idx];
+        self.emit(serde_json::json!({"event": "finished", "job": job.name, "label": job.label(),
+            "instance": job.instance_name, "result": state.results[idx].map(|v| v.as_str()), "message": state.v[idx],
+            "build_url": state.urls[idx], "duration": state.durations[idx].as_secs()}));
+    }
+
+    fn on_gate(&mut self, state: &RunState, gate: usize, end: usize) {
+        let jobs: Vec<_> = state.jobs[gate..end].iter()
+            .map(|v| serde_json::json!({"job": v.name, "instance": v.instance_name})).collect();
+        self.emit(serde_json::json!({"event": "gate", "next": jobs}));
+    }
+
+    fn on_rollback(&mut self, state: &RunState, idx: usize) {
+        let job = &state.jobs[idx];
+        self.emit(serde_json::json!({"event": "rollback", "job": job.name, "instance": job.instance_name,
+            "rollback_job": job.rollback_job}));
+    }
+
+    fn on_promotion(&mut self, state: &RunState, idx: usize, build_url: &str) {
+        let job = &state.jobs[idx];
+        self.emit(serde_json::json!({"event": "promotion", "job": job.name, "instance": job.instance_name,
+            "canary_url": build_url}));
+    }
+
+    fn on_timings(&mut self, state: &RunState) {
+        for (idx, v) in state.timings.iter().enumerate() {
+            if let Some(v) = v {
+                self.emit(serde_json::json!({"event": "timings", "job": state.jobs[idx].name,
+                    "instance": state.jobs[idx].instance_name, "trigger": v.trigger.as_secs_f64(),
+                    "queue": v.queue.as_secs_f64(), "build": v.build.as_secs_f64(),
+                    "polling": v.polling.as_secs_f64(), "total": v.total.as_secs_f64()}));
+            }
+        }
+    }
+
+    fn on_summary(&mut self, state: &RunState) {
+        let queue_wait = state.queue_stats().map(|v| serde_json::json!({
+            "p50": v.p50.as_secs(), "p95": v.p95.as_secs(), "average": v.average.as_secs(),
+            "longest": v.worst.iter().map(|(idx, wait)| serde_json::json!({"job": state.jobs[*idx].name,
+                "seconds": wait.as_secs()})).collect::<Vec<_>>(),
+            "exceeded": v.exceeded.is_some()}));
+        self.emit(serde_json::json!({"event": "summary", "total": state.jobs.len(),
+            "success": state.succeeded(), "exit_code": state.exit_code(), "queue_wait": queue_wait}));
+    }
+}
+
+// the events of JsonReporter piped to the renderer, which is waited for once its stdin is closed so
+// it can draw the end of the run
+pub struct ExternalReporter {
+    json: JsonReporter,
+    child: process::Child,
+}
+
+impl Reporter for ExternalReporter {
+    fn on_triggered(&mut self, state: &RunState, idx: usize) {
+        self.json.on_triggered(state, idx)
+    }
+
+    fn on_progress(&mut self, state: &RunState, idx: usize) {
+        self.json.on_progress(state, idx)
+    }
+
+    fn on_finished(&mut self, state: &RunState, idx: usize) {
+        self.json.on_finished(state, idx)
+    }
+
+    fn on_gate(&mut self, state: &RunState, gate: usize, end: usize) {
+        self.json.on_gate(state, gate, end)
+    }
+
+    fn on_rollback(&mut self, state: &RunState, idx: usize) {
+        self.json.on_rollback(state, idx)
+    }
+
+    fn on_promotion(&mut self, state: &RunState, idx: usize, build_url: &str) {
+        self.json.on_promotion(state, idx, build_url)
+    }
+
+    fn on_timings(&mut self, state: &RunState) {
+        self.json.on_timings(state)
+    }
+
+    fn on_summary(&mut self, state: &RunState) {
+        self.json.on_summary(state)
+    }
+}
+
+impl Drop for ExternalReporter {
+    fn drop(&mut self) {
+        self.json.out = Box::new(std::io::sink());
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(all(test, feature = "tui"))]
+mod tests {
+    use super::*;
+    use crate::tests::context;
+    use crate::BuildResult;
+
+    #[tokio::test]
+    async fn compact_prints_a_changed_result_again() {
+        let ctx = context("").await;
+        let jobs = vec![crate::get_job_config(&ctx, "api", "dev").unwrap()];
+        let mut p = RunState::new(&jobs);
+        let mut reporter = LiveReporter::new(true, None, false);
+        p.finish(0, Some(BuildResult::Failure), String::from("FAILURE"));
+        reporter.on_finished(&p, 0);
+        p.finish(0, Some(BuildResult::Failure), String::from("FAILURE (on_failure failed)"));
+        reporter.on_finished(&p, 0);
+        // not drawn yet, the pending line is replaced
+        assert_eq!(reporter.finished_lines, vec![(0, String::from("api -> FAILURE (on_failure failed)"))]);
+        reporter.finished_lines.clear();
+        reporter.on_finished(&p, 0);
+        assert!(reporter.finished_lines.is_empty());
+        p.finish(0, Some(BuildResult::Failure), String::from("FAILURE (rolled back: SUCCESS)"));
+        reporter.on_finished(&p, 0);
+        assert_eq!(reporter.finished_lines, vec![(0, String::from("api -> FAILURE (rolled back: SUCCESS)"))]);
+    }
+}
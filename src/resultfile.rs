@@ -0,0 +1,28 @@
+use std::fs;
+use std::path::PathBuf;
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::journal::JobRecord;
+
+// the `--result-file`, so wrapper scripts don't have to parse the output
+#[derive(Serialize)]
+pub struct RunResult<'a> {
+    // success, failure, timeout, interrupted or error
+    pub status: &'a str,
+    pub exit_code: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub total: usize,
+    pub success: usize,
+    pub jobs: Vec<JobRecord<'a>>,
+}
+
+// written to a temporary file first, a reader never sees a partial result
+pub fn write(path: &str, result: &RunResult) -> Result<()> {
+    let path = PathBuf::from(path);
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(result)?).with_context(|| format!("Failed to write {:?}", &tmp))?;
+    fs::rename(&tmp, &path).with_context(|| format!("Failed to write {:?}", &path))?;
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::audit;
+
+// written into the lock file so the next one knows who is running
+#[derive(Serialize, Deserialize, Debug)]
+struct Owner {
+    user: String,
+    host: String,
+    pid: u32,
+    // seconds since the epoch
+    since: u64,
+}
+
+// a run of the same jobs file, held as `<jobs file>.lock` until dropped
+pub struct RunLock {
+    path: PathBuf,
+}
+
+impl Owner {
+    fn current() -> Self {
+        Owner {
+            user: audit::user(),
+            host: audit::host(),
+            pid: std::process::id(),
+            since: time::SystemTime::now().duration_since(time::UNIX_EPOCH).unwrap_or_default().as_secs(),
+        }
+    }
+
+    // a lock left behind by a crashed run on this host, only detectable where /proc exists
+    fn is_stale(&self) -> bool {
+        self.host == audit::host() && PathBuf::from("/proc/self").exists()
+            && !PathBuf::from(format!("/proc/{}", self.pid)).exists()
+    }
+}
+
+impl RunLock {
+    // takes the lock of the jobs file, waiting for the other run when `wait` is set
+    pub async fn acquire(jobs_file: &str, wait: bool) -> Result<RunLock> {
+        let path = PathBuf::from(format!("{}.lock", jobs_file));
+        let content = serde_json::to_string(&Owner::current())?;
+        let mut waiting = false;
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut f) => {
+                    f.write_all(content.as_bytes()).with_context(|| format!("Failed to write {:?}", &path))?;
+                    return Ok(RunLock { path })
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e).with_context(|| format!("Failed to create the lock file {:?}", &path))
+            }
+            // the owner may still be writing the file, treat unreadable content as a live lock
+            let owner = fs::read_to_string(&path).ok().and_then(|v| serde_json::from_str::<Owner>(&v).ok());
+            if let Some(owner) = &owner {
+                if owner.is_stale() {
+                    let _ = fs::remove_file(&path);
+                    continue
+                }
+            }
+            if !wait {
+                return Err(match owner {
+                    Some(v) => anyhow!("Run already in progress by {}@{} (pid {}, started {}s ago), \
+                        use --wait-lock to wait for it or remove {:?} if it is stale", v.user, v.host, v.pid,
+                        Owner::current().since.saturating_sub(v.since), &path),
+                    None => anyhow!("Run already in progress, {:?} exists", &path)
+                })
+            }
+            if !waiting {
+                waiting = true;
+                if let Some(v) = &owner {
+                    println!("Waiting for the run of {}@{} (pid {}) to finish", v.user, v.host, v.pid);
+                }
+            }
+            tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+        }
+    }
+}
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+#[cfg(feature = "secrets")]
+use std::time;
+use anyhow::{anyhow, Result};
+#[cfg(feature = "secrets")]
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::{get_job_config, AppContext, _JenkinsJobConfig};
+#[cfg(feature = "secrets")]
+use crate::expand_template;
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SecretsConfig {
+    #[cfg(feature = "secrets")]
+    vault: Option<VaultConfig>,
+    #[cfg(feature = "secrets")]
+    aws: Option<AwsConfig>,
+}
+
+// `aws-sm:` and `aws-ssm:` references are read with the aws cli, so the usual credential chain
+// (environment, profile, instance or container role) applies
+#[cfg(feature = "secrets")]
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct AwsConfig {
+    region: Option<String>,
+    profile: Option<String>,
+}
+
+#[cfg(feature = "secrets")]
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+enum VaultAuth {
+    // `token`, or VAULT_TOKEN in the environment
+    #[default]
+    Token,
+    // `role_id` and `secret_id`, or VAULT_ROLE_ID and VAULT_SECRET_ID in the environment
+    Approle,
+}
+
+#[cfg(feature = "secrets")]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VaultConfig {
+    // defaults to VAULT_ADDR in the environment
+    address: Option<String>,
+    auth: Option<VaultAuth>,
+    token: Option<String>,
+    role_id: Option<String>,
+    secret_id: Option<String>,
+    // mount path of the approle auth method, defaults to approle
+    approle_mount: Option<String>,
+}
+
+#[cfg(feature = "secrets")]
+impl std::fmt::Debug for VaultConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VaultConfig")
+            .field("address", &self.address)
+            .field("auth", &self.auth)
+            .field("role_id", &self.role_id)
+            .finish()
+    }
+}
+
+// references look like `vault:<path>#<key>`, `aws-sm:<arn>[#<key>]` and `aws-ssm:<name>`
+pub fn is_reference(s: &str) -> bool {
+    ["vault:", "aws-sm:", "aws-ssm:"].iter().any(|v| s.starts_with(v))
+}
+
+// every config value that may hold a reference: credentials, headers and job parameters
+fn references(ctx: &AppContext) -> Vec<&str> {
+    let mut references = Vec::new();
+    for instance in &ctx.config.jenkins.instances {
+        references.push(instance.password.as_str());
+        references.extend(instance.session_cookie.as_deref());
+        references.extend(instance.extra_headers.iter().flat_map(|v| v.values()).map(|v| v.as_str()));
+        references.extend(instance.default_parameters.iter().flat_map(|v| v.values()).map(|v| v.as_str()));
+        for job in instance.jobs.iter().flat_map(|v| v.values()) {
+            let parameters = [&job.parameters, &job.rollback_parameters, &job.canary_parameters];
+            references.extend(parameters.iter().copied().flatten().flat_map(|v| v.values()).map(|v| v.as_str()));
+        }
+    }
+    references.retain(|v| is_reference(v));
+    references.sort_unstable();
+    references.dedup();
+    references
+}
+
+// the parameters of the jobs of the run, with the ones of the job file lines and of the env files, and
+// of their rollback jobs; the references not resolved with the config yet
+fn job_references<'a>(ctx: &AppContext, jobs: &'a [_JenkinsJobConfig],
+                      rollbacks: &'a [_JenkinsJobConfig]) -> Vec<&'a str> {
+    let mut references = Vec::new();
+    for job in jobs.iter().chain(rollbacks) {
+        let parameters = [&job.parameters, &job.rollback_parameters, &job.canary_parameters];
+        references.extend(parameters.iter().copied().flatten().flat_map(|v| v.values()).map(|v| v.as_str()));
+    }
+    references.retain(|v| is_reference(v) && !ctx.is_secret(v));
+    references.sort_unstable();
+    references.dedup();
+    references
+}
+
+// resolves every reference in the config before anything is sent to jenkins, reference -> secret
+pub async fn resolve_all(ctx: &AppContext) -> Result<HashMap<String, String>> {
+    resolve(ctx, references(ctx)).await
+}
+
+// the references only known once the jobs of the run are, before any of them is triggered
+pub async fn resolve_jobs(ctx: &AppContext, jobs: &[_JenkinsJobConfig]) -> Result<HashMap<String, String>> {
+    let mut rollbacks = Vec::new();
+    for job in jobs {
+        if let Some(name) = &job.rollback_job {
+            rollbacks.push(get_job_config(ctx, name, &job.instance_name)?);
+        }
+    }
+    resolve(ctx, job_references(ctx, jobs, &rollbacks)).await
+}
+
+#[cfg(feature = "secrets")]
+async fn resolve(ctx: &AppContext, references: Vec<&str>) -> Result<HashMap<String, String>> {
+    let mut resolved = HashMap::new();
+    if !references.is_empty() {
+        let client = reqwest::Client::builder().timeout(time::Duration::from_secs(10)).build()?;
+        let mut vault_token = None;
+        for reference in references {
+            let value = match reference.split_once(':') {
+                Some(("vault", rest)) => {
+                    let vault = ctx.config.secrets.as_ref().and_then(|v| v.vault.as_ref()).with_context(
+                        || format!("{:?} needs the [secrets.vault] configuration", reference))?;
+                    if vault_token.is_none() {
+                        vault_token = Some(vault.login(&client).await?);
+                    }
+                    vault.read(ctx, &client, vault_token.as_deref().unwrap_or_default(), rest).await
+                        .with_context(|| format!("Failed to resolve {:?}", reference))?
+                }
+                Some((kind @ ("aws-sm" | "aws-ssm"), rest)) => {
+                    let aws = ctx.config.secrets.as_ref().and_then(|v| v.aws.as_ref());
+                    read_aws(aws, kind, rest).await.with_context(|| format!("Failed to resolve {:?}", reference))?
+                }
+                _ => continue
+            };
+            resolved.insert(reference.to_string(), value);
+        }
+    }
+    Ok(resolved)
+}
+
+// a reference would be sent to jenkins as it is
+#[cfg(not(feature = "secrets"))]
+async fn resolve(_ctx: &AppContext, references: Vec<&str>) -> Result<HashMap<String, String>> {
+    match references.first() {
+        Some(v) => Err(anyhow!("{:?} needs jenkins-build built with the secrets feature", v)),
+        None => Ok(HashMap::new())
+    }
+}
+
+#[cfg(feature = "secrets")]
+#[derive(Deserialize)]
+struct VaultLogin {
+    auth: VaultLoginAuth,
+}
+
+#[cfg(feature = "secrets")]
+#[derive(Deserialize)]
+struct VaultLoginAuth {
+    client_token: String,
+}
+
+#[cfg(feature = "secrets")]
+#[derive(Deserialize)]
+struct VaultSecret {
+    data: serde_json::Map<String, serde_json::Value>,
+}
+
+#[cfg(feature = "secrets")]
+impl VaultConfig {
+    fn address(&self) -> Result<String> {
+        let address = match &self.address {
+            Some(v) => v.clone(),
+            None => std::env::var("VAULT_ADDR").context("Missing secrets.vault.address or VAULT_ADDR")?
+        };
+        Ok(address.trim_end_matches('/').to_string())
+    }
+
+    async fn login(&self, client: &reqwest::Client) -> Result<String> {
+        match self.auth.unwrap_or_default() {
+            VaultAuth::Token => match &self.token {
+                Some(v) => Ok(v.clone()),
+                None => std::env::var("VAULT_TOKEN").context("Missing secrets.vault.token or VAULT_TOKEN")
+            },
+            VaultAuth::Approle => {
+                let role_id = match &self.role_id {
+                    Some(v) => v.clone(),
+                    None => std::env::var("VAULT_ROLE_ID").context("Missing secrets.vault.role_id or VAULT_ROLE_ID")?
+                };
+                let secret_id = match &self.secret_id {
+                    Some(v) => v.clone(),
+                    None => std::env::var("VAULT_SECRET_ID")
+                        .context("Missing secrets.vault.secret_id or VAULT_SECRET_ID")?
+                };
+                let url = format!("{}/v1/auth/{}/login", self.address()?,
+                                  self.approle_mount.as_deref().unwrap_or("approle"));
+                let login = client.post(&url).json(&serde_json::json!({"role_id": role_id, "secret_id": secret_id}))
+                    .send().await.and_then(|r| r.error_for_status())
+                    .with_context(|| format!("Failed to log in to vault on {:?}", &url))?
+                    .json::<VaultLogin>().await.with_context(|| format!("Failed to deserialize json on {:?}", &url))?;
+                Ok(login.auth.client_token)
+            }
+        }
+    }
+
+    // reads `<path>#<key>`, the path may use the variables like `secret/data/{env}/jenkins#password`,
+    // both kv version 1 and 2 (where the path contains `data/`) are understood
+    async fn read(&self, ctx: &AppContext, client: &reqwest::Client, token: &str, reference: &str) -> Result<String> {
+        let (path, key) = reference.split_once('#').with_context(
+            || format!("Missing `#<key>` in {:?}", reference))?;
+        let path = expand_template(path, &ctx.variables)?;
+        let url = format!("{}/v1/{}", self.address()?, path.trim_start_matches('/'));
+        let secret = client.get(&url).header("X-Vault-Token", token).send().await
+            .and_then(|r| r.error_for_status())
+            .with_context(|| format!("Failed to read {:?} from vault", &path))?
+            .json::<VaultSecret>().await.with_context(|| format!("Failed to deserialize json on {:?}", &url))?;
+        let data = match secret.data.get("data") {
+            Some(serde_json::Value::Object(v)) => v,
+            _ => &secret.data
+        };
+        match data.get(key) {
+            Some(serde_json::Value::String(v)) => Ok(v.clone()),
+            Some(v) => Ok(v.to_string()),
+            None => Err(anyhow!("No key {:?} in {:?}", key, &path))
+        }
+    }
+}
+
+// `aws-sm:<arn or name>` is the secret string, `#<key>` picks a field of a json secret,
+// `aws-ssm:<name>` is the decrypted parameter
+#[cfg(feature = "secrets")]
+async fn read_aws(config: Option<&AwsConfig>, kind: &str, reference: &str) -> Result<String> {
+    let (id, key) = match reference.split_once('#') {
+        Some((id, key)) if kind == "aws-sm" => (id, Some(key)),
+        _ => (reference, None)
+    };
+    let mut cmd = std::process::Command::new("aws");
+    if kind == "aws-sm" {
+        cmd.args(["secretsmanager", "get-secret-value", "--secret-id", id, "--query", "SecretString"]);
+    } else {
+        cmd.args(["ssm", "get-parameter", "--with-decryption", "--name", id, "--query", "Parameter.Value"]);
+    }
+    cmd.args(["--output", "text"]);
+    if let Some(region) = config.and_then(|v| v.region.as_ref()) {
+        cmd.args(["--region", region]);
+    }
+    if let Some(profile) = config.and_then(|v| v.profile.as_ref()) {
+        cmd.args(["--profile", profile]);
+    }
+    cmd.stdin(std::process::Stdio::null());
+    let output = tokio::task::spawn_blocking(move || cmd.output()).await?
+        .context("Failed to execute the aws cli")?;
+    if !output.status.success() {
+        return Err(anyhow!("aws cli {}: {}", output.status, String::from_utf8_lossy(&output.stderr).trim()))
+    }
+    let value = String::from_utf8(output.stdout)?.trim_end_matches(['\r', '\n']).to_string();
+    let key = match key {
+        Some(v) => v,
+        None => return Ok(value)
+    };
+    let secret: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&value)
+        .with_context(|| format!("{:?} is not a json secret", id))?;
+    match secret.get(key) {
+        Some(serde_json::Value::String(v)) => Ok(v.clone()),
+        Some(v) => Ok(v.to_string()),
+        None => Err(anyhow!("No key {:?} in {:?}", key, id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry_config;
+    use crate::tests::context;
+
+    #[tokio::test]
+    async fn references_of_job_lines_and_env_files() {
+        let ctx = context("").await;
+        let path = std::env::temp_dir().join(format!("jenkins-build-secrets-{}.env", std::process::id()));
+        std::fs::write(&path, "TOKEN=vault:secret/api#token\n").unwrap();
+        let mut api = get_job_config(&ctx, "api", "dev").unwrap();
+        let merged = api.merge_env_file(Some(&path.to_str().unwrap().to_string()), Some(&vec![String::from("TOKEN")]));
+        let _ = std::fs::remove_file(&path);
+        merged.unwrap();
+        let web = entry_config(&ctx, "web", "dev", Some("KEY=vault:secret/web#key PLAIN=x")).unwrap();
+        let jobs = vec![api, web];
+        assert_eq!(job_references(&ctx, &jobs, &[]), ["vault:secret/api#token", "vault:secret/web#key"]);
+        ctx.add_resolved(HashMap::from([(String::from("vault:secret/web#key"), String::from("k"))]));
+        assert_eq!(job_references(&ctx, &jobs, &[]), ["vault:secret/api#token"]);
+        assert_eq!(ctx.resolve("vault:secret/web#key").unwrap(), "k");
+        assert_eq!(ctx.resolve("x").unwrap(), "x");
+        // never sent to jenkins as it is
+        assert!(ctx.resolve("vault:secret/api#token").is_err());
+    }
+
+    #[tokio::test]
+    async fn aws_references_of_job_lines_and_rollback_jobs() {
+        let ctx = context("").await;
+        let api = entry_config(&ctx, "api", "dev", Some("TOKEN=aws-ssm:/api/token KEY=aws-sm:arn:key#value")).unwrap();
+        let mut rollback = get_job_config(&ctx, "api-rollback", "dev").unwrap();
+        rollback.parameters = Some(HashMap::from([(String::from("TOKEN"), String::from("aws-sm:rollback"))]));
+        assert_eq!(job_references(&ctx, &[api], &[rollback]),
+                   ["aws-sm:arn:key#value", "aws-sm:rollback", "aws-ssm:/api/token"]);
+        assert!(ctx.resolve("aws-sm:rollback").is_err());
+        assert!(ctx.resolve("aws-ssm:/api/token").is_err());
+    }
+
+    #[cfg(not(feature = "secrets"))]
+    #[tokio::test]
+    async fn references_need_the_feature() {
+        let ctx = context("").await;
+        let web = entry_config(&ctx, "web", "dev", Some("KEY=vault:secret/web#key")).unwrap();
+        let e = resolve_jobs(&ctx, &[web]).await.unwrap_err();
+        assert!(e.to_string().contains("secrets feature"), "{}", e);
+    }
+}
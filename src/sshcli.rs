@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Command, Stdio};
+use anyhow::{anyhow, Context, Result};
+
+// `transport = "ssh"`: the build is triggered and followed through the ssh server of jenkins, like
+// `ssh -p 50022 user@host build team/api -f -p KEY=VALUE`, for instances that let the user read over http
+// but only trigger through the cli; the public key of the user has to be added to the jenkins user
+pub struct Target {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+}
+
+// the full name the cli expects, `team/api` for `team/job/api`
+fn full_name(job: &str) -> String {
+    job.split("/job/").collect::<Vec<_>>().join("/")
+}
+
+// ssh joins its arguments into one command line, jenkins splits it again and unquotes `"…"` with `\` escapes
+fn quote(s: &str) -> String {
+    if !s.is_empty() && s.chars().all(|c| c.is_alphanumeric() || "-_./=:,@%+".contains(c)) {
+        return s.to_string()
+    }
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn command(target: &Target, job: &str, parameters: &HashMap<String, String>) -> Command {
+    let mut cmd = Command::new("ssh");
+    // a password prompt would hang the run, only keys are used
+    cmd.arg("-o").arg("BatchMode=yes").arg("-p").arg(target.port.to_string())
+        .arg(format!("{}@{}", target.user, target.host))
+        .arg("build").arg(quote(&full_name(job))).arg("-f");
+    let mut parameters: Vec<_> = parameters.iter().collect();
+    parameters.sort();
+    for (key, value) in parameters {
+        cmd.arg("-p").arg(quote(&format!("{}={}", key, value)));
+    }
+    cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    cmd
+}
+
+// `Started team/api #12`, then `Completed team/api #12 : SUCCESS` once the build is done
+fn parse_line(line: &str) -> Option<(bool, u64, Option<&str>)> {
+    let (started, rest) = match line.split_once(' ')? {
+        ("Started", rest) => (true, rest),
+        ("Completed", rest) => (false, rest),
+        _ => return None
+    };
+    let (number, result) = match rest.rsplit_once(" : ") {
+        Some((v, result)) => (v, Some(result.trim())),
+        None => (rest, None)
+    };
+    let number = number.rsplit_once(" #")?.1.trim().parse().ok()?;
+    Some((started, number, result))
+}
+
+// triggers the build and waits for its result, `started` gets the number of the build once it left the queue
+pub async fn build(target: &Target, job: &str, parameters: &HashMap<String, String>,
+                   mut started: impl FnMut(u64)) -> Result<String> {
+    let mut cmd = command(target, job, parameters);
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let child = tokio::task::spawn_blocking(move || -> Result<(std::process::ExitStatus, String)> {
+        let mut child = cmd.spawn().context("Failed to run ssh")?;
+        let mut stderr = child.stderr.take().context("No stderr")?;
+        // read aside, a full pipe would block ssh while its stdout is read
+        let errors = std::thread::spawn(move || {
+            let mut s = String::new();
+            let _ = stderr.read_to_string(&mut s);
+            s
+        });
+        for line in BufReader::new(child.stdout.take().context("No stdout")?).lines() {
+            let _ = tx.send(line?);
+        }
+        let status = child.wait()?;
+        Ok((status, errors.join().unwrap_or_default()))
+    });
+    let mut result = None;
+    while let Some(line) = rx.recv().await {
+        match parse_line(&line) {
+            Some((true, number, _)) => started(number),
+            Some((false, _, Some(v))) => result = Some(v.to_string()),
+            _ => {}
+        }
+    }
+    let (status, errors) = child.await??;
+    result.ok_or_else(|| anyhow!("ssh build of {} on {}:{} ended without a result ({}): {}",
+                                 job, target.host, target.port, status, errors.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_started_and_completed() {
+        assert_eq!(parse_line("Started team/api #12"), Some((true, 12, None)));
+        assert_eq!(parse_line("Completed team/api #12 : SUCCESS"), Some((false, 12, Some("SUCCESS"))));
+        assert_eq!(parse_line("Completed team/api #12 : UNSTABLE \r"), Some((false, 12, Some("UNSTABLE"))));
+        // the name of the job may hold spaces and #
+        assert_eq!(parse_line("Started team/my api #2 #7"), Some((true, 7, None)));
+        assert_eq!(parse_line("Completed a : b #3 : FAILURE"), Some((false, 3, Some("FAILURE"))));
+        assert_eq!(parse_line("Started team/api"), None);
+        assert_eq!(parse_line("Started team/api #next"), None);
+        assert_eq!(parse_line("Finished: SUCCESS"), None);
+        assert_eq!(parse_line(""), None);
+    }
+
+    #[test]
+    fn quote_arguments() {
+        assert_eq!(quote("team/api"), "team/api");
+        assert_eq!(quote("KEY=a,b:c@d%e+f"), "KEY=a,b:c@d%e+f");
+        assert_eq!(quote(""), "\"\"");
+        assert_eq!(quote("KEY=two words"), "\"KEY=two words\"");
+        assert_eq!(quote("KEY=$(id);`id`"), "\"KEY=$(id);`id`\"");
+        assert_eq!(quote(r#"KEY=say "hi" \o/"#), r#""KEY=say \"hi\" \\o/""#);
+        assert_eq!(quote("KEY=a\nb"), "\"KEY=a\nb\"");
+    }
+
+    #[test]
+    fn command_line() {
+        let target = Target { host: String::from("ci.example.com"), port: 50022, user: String::from("bob") };
+        let parameters = HashMap::from([
+            (String::from("TAG"), String::from("v 1")),
+            (String::from("ENV"), String::from("prod")),
+        ]);
+        let cmd = command(&target, "team/job/api", &parameters);
+        assert_eq!(cmd.get_program(), "ssh");
+        let args: Vec<_> = cmd.get_args().map(|v| v.to_str().unwrap()).collect();
+        assert_eq!(args, ["-o", "BatchMode=yes", "-p", "50022", "bob@ci.example.com", "build", "team/api", "-f",
+                          "-p", "ENV=prod", "-p", "\"TAG=v 1\""]);
+    }
+}
@@ -0,0 +1,103 @@
+use std::fs;
+use std::path::PathBuf;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::events::JobEvent;
+use crate::_JenkinsJobConfig;
+
+// the progress of a job in the `--state` file
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct JobState {
+    pub job: String,
+    pub instance: String,
+    // set once the build was created, a resumed run polls it instead of triggering the job again
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_url: Option<String>,
+    // set once triggered, a run interrupted while the build was in the queue waits for it from there
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queue_item: Option<String>,
+    pub finished: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+// the jobs of a run in order, rewritten after every change
+pub struct StateFile {
+    path: PathBuf,
+    jobs: Vec<JobState>,
+}
+
+impl StateFile {
+    pub fn new(path: &str, jobs: &[_JenkinsJobConfig]) -> Self {
+        let jobs = jobs.iter().map(|v| JobState {
+            job: v.name.clone(),
+            instance: v.instance_name.clone(),
+            ..Default::default()
+        }).collect();
+        StateFile { path: PathBuf::from(path), jobs }
+    }
+
+    // the state of an interrupted run, which must have had the same jobs
+    pub fn load(path: &str, jobs: &[_JenkinsJobConfig]) -> Result<Self> {
+        let content = fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+        let states: Vec<JobState> = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {:?}", path))?;
+        let same = states.len() == jobs.len()
+            && states.iter().zip(jobs).all(|(s, j)| s.job == j.name && s.instance == j.instance_name);
+        if !same {
+            return Err(anyhow!("{:?} is the state of another job list, it can't be resumed", path))
+        }
+        Ok(StateFile { path: PathBuf::from(path), jobs: states })
+    }
+
+    pub fn jobs(&self) -> &[JobState] {
+        &self.jobs
+    }
+
+    // written to a temporary file first, so a crash never leaves a truncated state
+    pub fn save(&self) -> Result<()> {
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(&self.jobs)?)
+            .with_context(|| format!("Failed to write {:?}", &tmp))?;
+        fs::rename(&tmp, &self.path).with_context(|| format!("Failed to write {:?}", &self.path))?;
+        Ok(())
+    }
+
+    // a run that completed has nothing left to resume
+    pub fn remove(&self) {
+        let _ = fs::remove_file(&self.path);
+    }
+
+    // follows the events of the jobs until the bus is dropped
+    pub async fn follow(mut self, mut rx: UnboundedReceiver<(usize, JobEvent)>) -> Self {
+        while let Some((idx, event)) = rx.recv().await {
+            let job = &mut self.jobs[idx];
+            match event {
+                // a retried job or the rollout after its canary, the previous build is over
+                JobEvent::Enqueued { queue_item, .. } => {
+                    job.queue_item = Some(queue_item);
+                    job.build_url = None;
+                }
+                JobEvent::Started { build_url, .. } => job.build_url = Some(build_url),
+                JobEvent::Finished { result, message } => {
+                    job.finished = true;
+                    job.result = Some(result.as_str().to_string());
+                    job.message = Some(message);
+                }
+                JobEvent::Errored { message } => {
+                    job.finished = true;
+                    job.message = Some(message);
+                }
+                _ => continue
+            }
+            if let Err(e) = self.save() {
+                eprintln!("{:?}", e);
+            }
+        }
+        self
+    }
+}